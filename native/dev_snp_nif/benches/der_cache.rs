@@ -0,0 +1,27 @@
+//! Cost of re-encoding a certificate on every `to_der`/fingerprint call,
+//! against reading the encoding cached by `DerCertificate`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use dev_snp_nif::certs::{ca_chain_from_kds_response, fingerprint, DerCertificate};
+use openssl::hash::MessageDigest;
+use std::hint::black_box;
+
+/// The Milan ASK and ARK as served by KDS.
+const MILAN_CERT_CHAIN: &[u8] = include_bytes!("../../../certificates/amd-vcek-v1-Milan-cert_chain.pem");
+
+fn der_cache(c: &mut Criterion) {
+    let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).expect("failed to parse fixture chain");
+    let cached = DerCertificate::new(ca.ask.clone());
+
+    c.bench_function("to_der", |b| b.iter(|| black_box(&ca.ask).to_der()));
+    c.bench_function("clone_der_cached", |b| b.iter(|| black_box(&cached).clone_der()));
+    c.bench_function("fingerprint", |b| {
+        b.iter(|| fingerprint(black_box(&ca.ask), MessageDigest::sha384()))
+    });
+    c.bench_function("fingerprint_cached", |b| {
+        b.iter(|| black_box(&cached).fingerprint(MessageDigest::sha384()))
+    });
+}
+
+criterion_group!(benches, der_cache);
+criterion_main!(benches);
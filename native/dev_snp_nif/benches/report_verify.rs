@@ -0,0 +1,24 @@
+//! Throughput of report signature verification, and of the structural
+//! pre-check that turns malformed input away before any crypto runs.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use dev_snp_nif::report::{quick_reject, verify_report_with_key, REPORT_LEN};
+use dev_snp_nif::test_util::generate_test_chain;
+use std::hint::black_box;
+
+fn report_verification(c: &mut Criterion) {
+    let (chain, report) = generate_test_chain().expect("failed to mint test chain");
+    let vcek_key = chain.vek.public_key().expect("failed to read VCEK key");
+    let raw = bincode::serialize(&report).expect("failed to encode report");
+    let truncated = &raw[..REPORT_LEN - 1];
+
+    c.bench_function("verify_report_with_key", |b| {
+        b.iter(|| verify_report_with_key(black_box(&vcek_key), black_box(&report)))
+    });
+    c.bench_function("quick_reject_truncated", |b| {
+        b.iter(|| quick_reject(black_box(truncated)))
+    });
+}
+
+criterion_group!(benches, report_verification);
+criterion_main!(benches);
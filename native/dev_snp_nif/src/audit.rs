@@ -0,0 +1,454 @@
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use serde_json::Value;
+use sev::certs::snp::{Certificate, Chain, Verifiable};
+use sev::firmware::guest::AttestationReport;
+use crate::cert_table::assemble_chain;
+use crate::certs::{chain_from_pem_file, check_chain_roles, check_chain_validity, check_tcb_consistency, eq_der};
+use crate::report::{parse_report_bytes, tcb_at_least, ReportPolicy};
+
+/// The outcome of one check performed by `audit_validate`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckOutcome {
+    /// Stable identifier of the check (e.g., `"ask_signs_vek"`).
+    pub name: &'static str,
+    /// Whether the check passed.
+    pub passed: bool,
+    /// Why the check failed, if it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Evidence of which checks `audit_validate` ran, in order, and how each went.
+///
+/// Validation stops at the first failing check, so a failed report lists the
+/// checks up to and including the one that failed.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValidationReport {
+    /// The checks performed, in the order they ran.
+    pub checks: Vec<CheckOutcome>,
+}
+
+impl ValidationReport {
+    /// Reports whether every performed check passed.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    /// Serializes the report as JSON for an audit trail.
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({
+            "passed": self.passed(),
+            "checks": self.checks,
+        })
+    }
+
+    /// Records a check's outcome and returns whether it passed.
+    fn record<E: ToString>(&mut self, name: &'static str, result: Result<(), E>) -> bool {
+        let passed = result.is_ok();
+        let detail = result.err().map(|err| err.to_string());
+        #[cfg(feature = "tracing")]
+        tracing::info!(check = name, passed, detail = detail.as_deref(), "validation check");
+        self.checks.push(CheckOutcome { name, passed, detail });
+        passed
+    }
+}
+
+/// Validates a report against its chain and an operator policy, recording
+/// each check that ran.
+///
+/// The checks, in order:
+/// - `ark_self_signature`, `ark_signs_ask`, `ask_signs_vek`: the chain links;
+/// - `chain_roles`: the ARK and ASK are CAs and the VCEK is not;
+/// - `chain_validity`: every certificate is within its validity period;
+/// - `chain_policy`: the certificates satisfy the policy (e.g., `max_validity`);
+/// - `report_signature`: the VCEK signed the report;
+/// - `vcek_tcb`: the VCEK was issued for the report's reported TCB;
+/// - `tcb_consistency`: the current TCB is not below the committed TCB;
+/// - `measurement`: the launch measurement matches the policy, if it pins one;
+/// - `policy`: the report satisfies the rest of the policy.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip_all, fields(chip_id = %hex::encode(report.chip_id)))
+)]
+pub fn audit_validate(chain: &Chain, report: &AttestationReport, policy: &ReportPolicy) -> ValidationReport {
+    let mut audit = ValidationReport::default();
+    let links = [
+        ("ark_self_signature", (&chain.ca.ark, &chain.ca.ark)),
+        ("ark_signs_ask", (&chain.ca.ark, &chain.ca.ask)),
+        ("ask_signs_vek", (&chain.ca.ask, &chain.vek)),
+    ];
+    for (name, link) in links {
+        if !audit.record(name, link.verify()) {
+            return audit;
+        }
+    }
+    if !audit.record("chain_roles", check_chain_roles(chain))
+        || !audit.record("chain_validity", check_chain_validity(chain, None))
+        || !audit.record("chain_policy", policy.check_chain(chain))
+        || !audit.record("report_signature", (chain, report).verify())
+        || !audit.record("vcek_tcb", check_tcb_consistency(&chain.vek, report))
+    {
+        return audit;
+    }
+
+    let tcb_consistent = if tcb_at_least(&report.current_tcb, &report.committed_tcb) {
+        Ok(())
+    } else {
+        Err("Current TCB is below the committed TCB")
+    };
+    if !audit.record("tcb_consistency", tcb_consistent) {
+        return audit;
+    }
+    if let Some(expected) = policy.measurement {
+        let pinned = ReportPolicy {
+            measurement: Some(expected),
+            ..ReportPolicy::default()
+        };
+        if !audit.record("measurement", pinned.check(report)) {
+            return audit;
+        }
+    }
+    audit.record("policy", policy.check(report));
+    audit
+}
+
+/// A failed check reported by `audit_validate_all`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationError {
+    /// Identifier of the failed check, as used by `audit_validate`.
+    pub check: &'static str,
+    /// Why the check failed.
+    pub detail: String,
+}
+
+/// Records a failed check in `errors`, returning whether the check passed.
+fn collect<E: ToString>(errors: &mut Vec<ValidationError>, check: &'static str, result: Result<(), E>) -> bool {
+    match result {
+        Ok(()) => true,
+        Err(err) => {
+            errors.push(ValidationError {
+                check,
+                detail: err.to_string(),
+            });
+            false
+        }
+    }
+}
+
+/// Runs the checks of `audit_validate` without stopping at the first
+/// failure, returning every failure found in one pass.
+///
+/// Checks that depend on another are still gated: the report signature is
+/// only checked once every chain link verifies. Each violated policy
+/// constraint is reported separately under the `policy` check.
+///
+/// # Returns
+/// The failures in check order; empty if the report is trustworthy.
+pub fn audit_validate_all(chain: &Chain, report: &AttestationReport, policy: &ReportPolicy) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let links = [
+        ("ark_self_signature", (&chain.ca.ark, &chain.ca.ark)),
+        ("ark_signs_ask", (&chain.ca.ark, &chain.ca.ask)),
+        ("ask_signs_vek", (&chain.ca.ask, &chain.vek)),
+    ];
+    let mut chain_verified = true;
+    for (name, link) in links {
+        chain_verified &= collect(&mut errors, name, link.verify());
+    }
+    collect(&mut errors, "chain_roles", check_chain_roles(chain));
+    collect(&mut errors, "chain_validity", check_chain_validity(chain, None));
+    collect(&mut errors, "chain_policy", policy.check_chain(chain));
+    if chain_verified {
+        collect(&mut errors, "report_signature", (chain, report).verify());
+    }
+    collect(&mut errors, "vcek_tcb", check_tcb_consistency(&chain.vek, report));
+    if !tcb_at_least(&report.current_tcb, &report.committed_tcb) {
+        collect(&mut errors, "tcb_consistency", Err("Current TCB is below the committed TCB"));
+    }
+    for violation in policy.violations(report) {
+        collect(&mut errors, "policy", Err(violation));
+    }
+    errors
+}
+
+/// Validates a binary report and the certificate table handed up with it
+/// under an operator policy, in one call.
+///
+/// The chain is assembled from the table, its ARK must match one of
+/// `trusted_roots` (recorded as the `trusted_root` check), and then every
+/// check of `audit_validate` runs.
+///
+/// # Errors
+/// Returns an error only if the report or the table cannot be parsed; failed
+/// checks are reported in the returned `ValidationReport`.
+pub fn validate_raw(
+    report_bytes: &[u8],
+    cert_table_bytes: &[u8],
+    policy: &ReportPolicy,
+    trusted_roots: &[Certificate],
+) -> Result<ValidationReport, Box<dyn std::error::Error>> {
+    let report = parse_report_bytes(report_bytes)?;
+    let chain = assemble_chain(cert_table_bytes)?;
+
+    let mut audit = ValidationReport::default();
+    let mut trusted = false;
+    for root in trusted_roots {
+        trusted |= eq_der(&chain.ca.ark, root)?;
+    }
+    let trusted = if trusted {
+        Ok(())
+    } else {
+        Err("Chain ARK does not match any trusted root")
+    };
+    if audit.record("trusted_root", trusted) {
+        audit.checks.extend(audit_validate(&chain, &report, policy).checks);
+    }
+    Ok(audit)
+}
+
+/// The outcome of validating one chain bundle, keyed by its path.
+pub type BundleResult = (PathBuf, Result<(), String>);
+
+/// Loads and validates a single `.pem` chain bundle.
+fn validate_bundle(path: &Path, policy: &ReportPolicy) -> Result<(), String> {
+    let chain = chain_from_pem_file(path).map_err(|err| err.to_string())?;
+    check_chain_validity(&chain, None).map_err(|err| err.to_string())?;
+    policy.check_chain(&chain)
+}
+
+/// Validates every `.pem` chain bundle in a directory, e.g. to audit a
+/// fleet's cached chains. With the `rayon` feature the bundles are validated
+/// in parallel; otherwise they are validated one after another.
+///
+/// Each bundle must hold an ARK, ASK and VCEK that verify, play their roles,
+/// are within their validity periods, and satisfy the policy's chain checks.
+///
+/// # Returns
+/// One result per bundle, sorted by path.
+///
+/// # Errors
+/// Returns an error only if the directory itself cannot be read.
+pub fn validate_dir<P: AsRef<Path>>(
+    dir: P,
+    policy: &ReportPolicy,
+) -> Result<Vec<BundleResult>, Box<dyn std::error::Error>> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "pem") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(validate_bundles(paths, policy))
+}
+
+/// Validates `paths` across rayon's thread pool, preserving their order.
+#[cfg(feature = "rayon")]
+fn validate_bundles(paths: Vec<PathBuf>, policy: &ReportPolicy) -> Vec<BundleResult> {
+    use rayon::prelude::*;
+
+    paths
+        .into_par_iter()
+        .map(|path| {
+            let result = validate_bundle(&path, policy);
+            (path, result)
+        })
+        .collect()
+}
+
+/// Validates `paths` one after another.
+#[cfg(not(feature = "rayon"))]
+fn validate_bundles(paths: Vec<PathBuf>, policy: &ReportPolicy) -> Vec<BundleResult> {
+    paths
+        .into_iter()
+        .map(|path| {
+            let result = validate_bundle(&path, policy);
+            (path, result)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cert_table::VCEK_GUID;
+    use crate::test_util::{chain_table, generate_test_chain, temp_dir};
+    use openssl::x509::X509;
+    use sev::firmware::host::TcbVersion;
+
+    /// Returns the names of the checks recorded in `audit`.
+    fn check_names(audit: &ValidationReport) -> Vec<&'static str> {
+        audit.checks.iter().map(|check| check.name).collect()
+    }
+
+    #[test]
+    fn enumerates_every_check_performed() {
+        let (chain, report) = generate_test_chain().unwrap();
+        let policy = ReportPolicy {
+            measurement: Some([0; 48]),
+            ..ReportPolicy::default()
+        };
+        let audit = audit_validate(&chain, &report, &policy);
+        assert!(audit.passed(), "{:?}", audit.checks);
+        assert_eq!(
+            check_names(&audit),
+            [
+                "ark_self_signature",
+                "ark_signs_ask",
+                "ask_signs_vek",
+                "chain_roles",
+                "chain_validity",
+                "chain_policy",
+                "report_signature",
+                "vcek_tcb",
+                "tcb_consistency",
+                "measurement",
+                "policy",
+            ]
+        );
+
+        let json = audit.to_json();
+        assert_eq!(json["passed"], true);
+        assert_eq!(json["checks"][0]["name"], "ark_self_signature");
+        assert!(json["checks"][0].get("detail").is_none());
+    }
+
+    #[test]
+    fn stops_at_the_first_failing_check() {
+        let (chain, report) = generate_test_chain().unwrap();
+        let policy = ReportPolicy {
+            measurement: Some([1; 48]),
+            ..ReportPolicy::default()
+        };
+        let audit = audit_validate(&chain, &report, &policy);
+        assert!(!audit.passed());
+        let last = audit.checks.last().unwrap();
+        assert_eq!(last.name, "measurement");
+        assert!(last.detail.as_deref().unwrap().contains("measurement"));
+    }
+
+    /// Collects the `check` field of every event emitted while installed.
+    #[cfg(feature = "tracing")]
+    struct CheckCollector(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+    #[cfg(feature = "tracing")]
+    impl tracing::field::Visit for CheckCollector {
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            if field.name() == "check" {
+                self.0.lock().unwrap().push(value.to_string());
+            }
+        }
+
+        fn record_debug(&mut self, _field: &tracing::field::Field, _value: &dyn std::fmt::Debug) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for CheckCollector {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            tracing::span::Id::from_u64(1)
+        }
+
+        fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            event.record(&mut CheckCollector(self.0.clone()));
+        }
+
+        fn enter(&self, _span: &tracing::span::Id) {}
+
+        fn exit(&self, _span: &tracing::span::Id) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn emits_an_event_per_check() {
+        let (chain, report) = generate_test_chain().unwrap();
+        let checks = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let audit = tracing::subscriber::with_default(CheckCollector(checks.clone()), || {
+            audit_validate(&chain, &report, &ReportPolicy::default())
+        });
+        assert_eq!(*checks.lock().unwrap(), check_names(&audit));
+    }
+
+    #[test]
+    fn validate_dir_flags_only_the_tampered_bundle() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let dir = temp_dir("validate-dir");
+        let mut valid = Vec::new();
+        for cert in [&chain.ca.ark, &chain.ca.ask, &chain.vek] {
+            valid.extend(cert.to_pem().unwrap());
+        }
+        std::fs::write(dir.join("a-valid.pem"), &valid).unwrap();
+
+        // Flip a bit in the VCEK's signature so it no longer verifies.
+        let mut vek_der = chain.vek.to_der().unwrap();
+        *vek_der.last_mut().unwrap() ^= 1;
+        let mut tampered = Vec::new();
+        for cert in [&chain.ca.ark, &chain.ca.ask] {
+            tampered.extend(cert.to_pem().unwrap());
+        }
+        tampered.extend(X509::from_der(&vek_der).unwrap().to_pem().unwrap());
+        std::fs::write(dir.join("b-tampered.pem"), &tampered).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a bundle").unwrap();
+
+        let results = validate_dir(&dir, &ReportPolicy::default()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, dir.join("a-valid.pem"));
+        assert_eq!(results[0].1, Ok(()));
+        assert_eq!(results[1].0, dir.join("b-tampered.pem"));
+        assert!(results[1].1.is_err());
+    }
+
+    #[test]
+    fn validate_raw_passes_matching_report_and_table() {
+        let (chain, report) = generate_test_chain().unwrap();
+        let report_bytes = bincode::serialize(&report).unwrap();
+        let table = chain_table(&chain, VCEK_GUID);
+        let policy = ReportPolicy {
+            measurement: Some([0; 48]),
+            ..ReportPolicy::default()
+        };
+
+        let audit = validate_raw(&report_bytes, &table, &policy, std::slice::from_ref(&chain.ca.ark)).unwrap();
+        assert!(audit.passed(), "{:?}", audit.checks);
+        assert_eq!(check_names(&audit).first(), Some(&"trusted_root"));
+        assert_eq!(check_names(&audit).last(), Some(&"policy"));
+
+        let (other, _) = generate_test_chain().unwrap();
+        let untrusted = validate_raw(&report_bytes, &table, &policy, &[other.ca.ark]).unwrap();
+        assert!(!untrusted.passed());
+        assert_eq!(check_names(&untrusted), ["trusted_root"]);
+    }
+
+    #[test]
+    fn audit_validate_all_reports_measurement_and_tcb_failures_together() {
+        let (chain, report) = generate_test_chain().unwrap();
+        let policy = ReportPolicy {
+            measurement: Some([0xab; 48]),
+            platform_tcb: Some(TcbVersion {
+                bootloader: 3,
+                tee: 0,
+                snp: 8,
+                microcode: 115,
+                _reserved: [0; 4],
+            }),
+            ..ReportPolicy::default()
+        };
+        assert!(audit_validate_all(&chain, &report, &ReportPolicy::default()).is_empty());
+
+        let errors = audit_validate_all(&chain, &report, &policy);
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(errors.iter().all(|error| error.check == "policy"));
+        assert!(errors[0].detail.contains("measurement"), "{}", errors[0].detail);
+        assert!(errors[1].detail.contains("platform TCB"), "{}", errors[1].detail);
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+use crate::clock::Clock;
+
+/// A cached value together with the time it was fetched.
+#[derive(Debug, Clone)]
+pub struct CacheEntry<V> {
+    /// The cached value.
+    pub value: V,
+    /// When the value was fetched, in seconds since the Unix epoch.
+    pub fetched_at: i64,
+}
+
+impl<V> CacheEntry<V> {
+    /// Returns how many seconds old the entry is at `now`.
+    pub fn age(&self, now: i64) -> i64 {
+        now - self.fetched_at
+    }
+}
+
+/// Cache of fetched endorsement material (e.g., VCEK chains) with an optional
+/// maximum age, bounding how long a revoked-but-cached VCEK can linger.
+///
+/// Entries older than `max_age` are treated as absent and refreshed on access.
+#[derive(Debug)]
+pub struct VcekCache<K, V> {
+    entries: HashMap<K, CacheEntry<V>>,
+    max_age: Option<Duration>,
+}
+
+impl<K: Eq + Hash, V: Clone> VcekCache<K, V> {
+    /// Creates an empty cache. `None` keeps entries until they are replaced.
+    pub fn new(max_age: Option<Duration>) -> Self {
+        VcekCache {
+            entries: HashMap::new(),
+            max_age,
+        }
+    }
+
+    /// Reports whether an entry is still within the configured maximum age.
+    fn is_fresh(&self, entry: &CacheEntry<V>, now: i64) -> bool {
+        match self.max_age {
+            Some(max_age) => entry.age(now) <= max_age.as_secs() as i64,
+            None => true,
+        }
+    }
+
+    /// Returns the entry for `key` if it is still fresh, evicting it otherwise.
+    pub fn get(&mut self, key: &K, clock: &dyn Clock) -> Option<&CacheEntry<V>> {
+        let now = clock.now_unix();
+        let fresh = self
+            .entries
+            .get(key)
+            .map(|entry| self.is_fresh(entry, now))?;
+        if !fresh {
+            self.entries.remove(key);
+            return None;
+        }
+        self.entries.get(key)
+    }
+
+    /// Stores a value fetched now.
+    pub fn insert(&mut self, key: K, value: V, clock: &dyn Clock) {
+        let entry = CacheEntry {
+            value,
+            fetched_at: clock.now_unix(),
+        };
+        self.entries.insert(key, entry);
+    }
+
+    /// Returns the cached value for `key`, calling `refresh` to fetch and
+    /// store a new one if the entry is missing or older than the maximum age.
+    ///
+    /// # Errors
+    /// Returns the error from `refresh`; the cache is left unchanged.
+    pub fn get_or_refresh<E>(
+        &mut self,
+        key: K,
+        clock: &dyn Clock,
+        refresh: impl FnOnce() -> Result<V, E>,
+    ) -> Result<V, E> {
+        if let Some(entry) = self.get(&key, clock) {
+            return Ok(entry.value.clone());
+        }
+        let value = refresh()?;
+        self.insert(key, value.clone(), clock);
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+
+    #[test]
+    fn refreshes_entries_older_than_max_age() {
+        let mut cache = VcekCache::new(Some(Duration::from_secs(60)));
+        let mut fetches = 0;
+        let mut fetch = |clock: &FixedClock| {
+            cache.get_or_refresh("chip", clock, || {
+                fetches += 1;
+                Ok::<_, ()>(fetches)
+            })
+        };
+
+        assert_eq!(fetch(&FixedClock(1_000)), Ok(1));
+        assert_eq!(fetch(&FixedClock(1_060)), Ok(1));
+        assert_eq!(fetch(&FixedClock(1_061)), Ok(2));
+        assert_eq!(fetch(&FixedClock(1_100)), Ok(2));
+    }
+
+    #[test]
+    fn leaves_cache_unchanged_when_refresh_fails() {
+        let mut cache = VcekCache::new(None);
+        let clock = FixedClock(0);
+        assert_eq!(cache.get_or_refresh("chip", &clock, || Err("offline")), Err("offline"));
+        assert!(cache.get(&"chip", &clock).is_none());
+        assert_eq!(cache.get_or_refresh("chip", &clock, || Ok::<_, &str>(7)), Ok(7));
+        assert_eq!(cache.get(&"chip", &clock).unwrap().value, 7);
+    }
+}
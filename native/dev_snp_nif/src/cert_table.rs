@@ -0,0 +1,245 @@
+use sev::certs::snp::{ca, Certificate, Chain, Verifiable};
+use sev::firmware::guest::AttestationReport;
+use crate::certs::{check_chain_roles, from_der};
+use crate::report::{parse_report_bytes, REPORT_LEN};
+
+/// Size in bytes of one cert-table entry: a 16-byte GUID, then offset and length.
+pub(crate) const ENTRY_LEN: usize = 24;
+
+// The GUIDs below are defined by the GHCB specification and are shared by
+// every product line; the table for a Genoa or Turin guest differs only in
+// the certificates it carries.
+
+/// GUID (little-endian encoding) of the ARK entry.
+pub const ARK_GUID: [u8; 16] = [
+    0xa4, 0x06, 0xb4, 0xc0, 0x03, 0xa8, 0x52, 0x49, 0x97, 0x43, 0x3f, 0xb6, 0x01, 0x4c, 0xd0, 0xae,
+];
+/// GUID (little-endian encoding) of the ASK entry.
+pub const ASK_GUID: [u8; 16] = [
+    0x79, 0xb3, 0xb7, 0x4a, 0xac, 0xbb, 0xe4, 0x4f, 0xa0, 0x2f, 0x05, 0xae, 0xf3, 0x27, 0xc7, 0x82,
+];
+/// GUID (little-endian encoding) of the VCEK entry.
+pub const VCEK_GUID: [u8; 16] = [
+    0x8d, 0x75, 0xda, 0x63, 0x64, 0xe6, 0x64, 0x45, 0xad, 0xc5, 0xf4, 0xb9, 0x3b, 0xe8, 0xac, 0xcd,
+];
+/// GUID (little-endian encoding) of the VLEK entry.
+pub const VLEK_GUID: [u8; 16] = [
+    0xc2, 0x4b, 0x07, 0xa8, 0x5a, 0xa2, 0x3e, 0x48, 0xaa, 0xe6, 0x39, 0xc0, 0x45, 0xa0, 0xb8, 0xa1,
+];
+
+/// One entry of the guest certificate table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertTableEntry {
+    /// The GUID identifying the certificate's role.
+    pub guid: [u8; 16],
+    /// The DER-encoded certificate.
+    pub der: Vec<u8>,
+}
+
+/// Parses the certificate table returned alongside an extended report.
+///
+/// The table is a list of `{ guid, offset, length }` entries terminated by an
+/// all-zero entry; offsets are relative to the start of the table.
+///
+/// # Errors
+/// Returns an error if the table is truncated or an entry points outside it.
+pub fn parse_cert_table(raw: &[u8]) -> Result<Vec<CertTableEntry>, Box<dyn std::error::Error>> {
+    let mut entries = Vec::new();
+    for header in raw.chunks(ENTRY_LEN) {
+        if header.len() < ENTRY_LEN {
+            return Err("Certificate table is missing its terminating entry".into());
+        }
+        if header.iter().all(|&b| b == 0) {
+            return Ok(entries);
+        }
+        let guid: [u8; 16] = header[..16].try_into()?;
+        let offset = u32::from_le_bytes(header[16..20].try_into()?) as usize;
+        let length = u32::from_le_bytes(header[20..24].try_into()?) as usize;
+        let der = offset
+            .checked_add(length)
+            .and_then(|end| raw.get(offset..end))
+            .ok_or("Certificate table entry points outside the table")?;
+        entries.push(CertTableEntry {
+            guid,
+            der: der.to_vec(),
+        });
+    }
+    Err("Certificate table is missing its terminating entry".into())
+}
+
+/// Assembles a `Chain` from a certificate table without verifying it.
+///
+/// The endorsement key is taken from the VCEK entry, or from the VLEK entry
+/// if the table carries one instead.
+///
+/// # Errors
+/// Returns an error naming any missing role, or if the table is malformed.
+pub(crate) fn assemble_chain(raw: &[u8]) -> Result<Chain, Box<dyn std::error::Error>> {
+    let entries = parse_cert_table(raw)?;
+    let find = |guid: &[u8; 16]| entries.iter().find(|entry| &entry.guid == guid);
+
+    // Step 1: Decode the CA certificates, which every table must carry.
+    let ark = from_der(&find(&ARK_GUID).ok_or("Certificate table is missing the ARK")?.der)?;
+    let ask = from_der(&find(&ASK_GUID).ok_or("Certificate table is missing the ASK")?.der)?;
+
+    // Step 2: Pick the endorsement key present in the table.
+    let vek_entry = find(&VCEK_GUID)
+        .or_else(|| find(&VLEK_GUID))
+        .ok_or("Certificate table carries neither a VCEK nor a VLEK")?;
+    let vek: Certificate = from_der(&vek_entry.der)?;
+
+    Ok(Chain {
+        ca: ca::Chain { ark, ask },
+        vek,
+    })
+}
+
+/// Builds a verified `Chain` directly from the certificate table a guest hands up.
+///
+/// The endorsement key is taken from the VCEK entry, or from the VLEK entry
+/// if the table carries one instead.
+///
+/// # Errors
+/// Returns an error naming any missing role, or if the table is malformed or
+/// the assembled chain does not verify.
+pub fn chain_from_cert_table(raw: &[u8]) -> Result<Chain, Box<dyn std::error::Error>> {
+    let chain = assemble_chain(raw)?;
+    (&chain).verify()?;
+    check_chain_roles(&chain)?;
+    Ok(chain)
+}
+
+/// Splits an extended report, as returned by `SNP_GET_EXT_REPORT`, into the
+/// report and the certificates of the table that follows it.
+///
+/// # Arguments
+/// * `raw` - The `REPORT_LEN`-byte binary report immediately followed by the
+///   certificate table.
+///
+/// # Returns
+/// The parsed report and the table's certificates, in table order.
+///
+/// # Errors
+/// Returns an error if the input is shorter than a report, the report does
+/// not parse, or the table or one of its certificates is malformed.
+pub fn parse_extended_report(
+    raw: &[u8],
+) -> Result<(AttestationReport, Vec<Certificate>), Box<dyn std::error::Error>> {
+    if raw.len() < REPORT_LEN {
+        return Err(format!(
+            "Extended report must start with a {REPORT_LEN}-byte report, got {} bytes",
+            raw.len()
+        )
+        .into());
+    }
+    let (report_bytes, table) = raw.split_at(REPORT_LEN);
+    let report = parse_report_bytes(report_bytes)?;
+    let certs = parse_cert_table(table)?
+        .iter()
+        .map(|entry| from_der(&entry.der))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((report, certs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{cert_table, chain_table, generate_test_chain};
+
+    #[test]
+    fn parses_entries_in_table_order() {
+        let table = cert_table(&[(ARK_GUID, vec![1, 2, 3]), (VCEK_GUID, vec![4, 5])]);
+        let entries = parse_cert_table(&table).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                CertTableEntry { guid: ARK_GUID, der: vec![1, 2, 3] },
+                CertTableEntry { guid: VCEK_GUID, der: vec![4, 5] },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_table_without_terminator() {
+        let table = cert_table(&[(ARK_GUID, vec![1, 2, 3])]);
+        assert!(parse_cert_table(&table[..ENTRY_LEN]).is_err());
+    }
+
+    #[test]
+    fn rejects_entry_pointing_outside_table() {
+        let mut table = cert_table(&[(ARK_GUID, vec![1, 2, 3])]);
+        table[20..24].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse_cert_table(&table).is_err());
+    }
+
+    #[test]
+    fn builds_verified_chain_from_vcek_table() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let built = chain_from_cert_table(&chain_table(&chain, VCEK_GUID)).unwrap();
+        assert_eq!(built.vek.to_der().unwrap(), chain.vek.to_der().unwrap());
+    }
+
+    #[test]
+    fn falls_back_to_vlek_entry() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let built = chain_from_cert_table(&chain_table(&chain, VLEK_GUID)).unwrap();
+        assert_eq!(built.vek.to_der().unwrap(), chain.vek.to_der().unwrap());
+    }
+
+    #[test]
+    fn rejects_table_missing_the_ark() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let table = cert_table(&[
+            (ASK_GUID, chain.ca.ask.to_der().unwrap()),
+            (VCEK_GUID, chain.vek.to_der().unwrap()),
+        ]);
+        let err = chain_from_cert_table(&table).unwrap_err();
+        assert!(err.to_string().contains("ARK"));
+    }
+
+    #[test]
+    fn rejects_table_mixing_chains() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let (other, _) = generate_test_chain().unwrap();
+        let table = cert_table(&[
+            (ARK_GUID, other.ca.ark.to_der().unwrap()),
+            (ASK_GUID, chain.ca.ask.to_der().unwrap()),
+            (VCEK_GUID, chain.vek.to_der().unwrap()),
+        ]);
+        assert!(chain_from_cert_table(&table).is_err());
+    }
+
+    #[test]
+    fn splits_extended_report_into_report_and_certs() {
+        let (chain, report) = generate_test_chain().unwrap();
+        let report_bytes = bincode::serialize(&report).unwrap();
+        let mut raw = report_bytes.clone();
+        raw.extend(chain_table(&chain, VCEK_GUID));
+
+        let (parsed, certs) = parse_extended_report(&raw).unwrap();
+        assert_eq!(bincode::serialize(&parsed).unwrap(), report_bytes);
+        let ders: Vec<Vec<u8>> = certs.iter().map(|cert| cert.to_der().unwrap()).collect();
+        assert_eq!(
+            ders,
+            vec![
+                chain.ca.ark.to_der().unwrap(),
+                chain.ca.ask.to_der().unwrap(),
+                chain.vek.to_der().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_extended_report_shorter_than_a_report() {
+        let (_, report) = generate_test_chain().unwrap();
+        let report_bytes = bincode::serialize(&report).unwrap();
+        assert!(parse_extended_report(&report_bytes[..REPORT_LEN - 1]).is_err());
+    }
+
+    #[test]
+    fn rejects_extended_report_without_cert_table() {
+        let (_, report) = generate_test_chain().unwrap();
+        let report_bytes = bincode::serialize(&report).unwrap();
+        assert!(parse_extended_report(&report_bytes).is_err());
+    }
+}
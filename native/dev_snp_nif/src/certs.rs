@@ -0,0 +1,2573 @@
+use openssl::asn1::{Asn1Time, Asn1TimeRef};
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::pkcs7::Pkcs7;
+use openssl::pkey::{Id, PKey, Public};
+use openssl::sign::Verifier;
+use openssl::nid::Nid;
+use openssl::x509::X509;
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use sev::certs::snp::{ca, Certificate, Chain, Verifiable};
+use sev::firmware::guest::AttestationReport;
+use sev::firmware::host::TcbVersion;
+use snafu::Snafu;
+use crate::clock::{Clock, SystemClock};
+use crate::ct::ct_eq;
+use crate::metrics::VerifyMetrics;
+use crate::product::ProductLine;
+use crate::report::reported_tcb;
+use crate::der::{
+    decode_oid, encode_oid, is_strict_der, read_all, read_tlv, TAG_BIT_STRING, TAG_BOOLEAN, TAG_INTEGER, TAG_OCTET_STRING, TAG_OID,
+    TAG_SEQUENCE, Tlv,
+};
+
+/// Errors raised while decoding certificate input, before OpenSSL is involved.
+#[derive(Debug, Snafu)]
+pub enum CertFormatError {
+    /// The input contained no bytes (or only whitespace).
+    #[snafu(display("Certificate input is empty"))]
+    EmptyInput,
+    /// The input is not valid text in the expected encoding (hex or base64).
+    #[snafu(display("Certificate input is not valid {encoding}: {reason}"))]
+    InvalidEncoding { encoding: &'static str, reason: String },
+    /// The input's format could not be determined with confidence.
+    #[snafu(display("Certificate input is neither armored PEM nor a well-formed DER SEQUENCE"))]
+    AmbiguousInput,
+    /// Text input that was expected to be PEM lacks a certificate armor line.
+    #[snafu(display("Certificate text is not PEM"))]
+    NotPem,
+}
+
+/// Armor line opening a plain PEM certificate.
+const PEM_CERT_HEADER: &[u8] = b"-----BEGIN CERTIFICATE-----";
+/// Armor line opening an OpenSSL trusted certificate (a trust anchor with
+/// auxiliary trust settings appended to the certificate DER).
+const PEM_TRUSTED_CERT_HEADER: &[u8] = b"-----BEGIN TRUSTED CERTIFICATE-----";
+/// Armor line closing an OpenSSL trusted certificate.
+const PEM_TRUSTED_CERT_FOOTER: &[u8] = b"-----END TRUSTED CERTIFICATE-----";
+
+/// Armor line opening a PEM PKCS#7 bundle.
+const PEM_PKCS7_HEADER: &[u8] = b"-----BEGIN PKCS7-----";
+/// DER contents of the PKCS#7 signedData content-type OID (1.2.840.113549.1.7.2).
+const OID_PKCS7_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+
+/// The encoding of a certificate input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertFormat {
+    /// Base64 PEM armor, either `CERTIFICATE` or `TRUSTED CERTIFICATE`.
+    Pem,
+    /// Raw DER bytes.
+    Der,
+    /// A PKCS#7 certificate bundle (`.p7b`/`.p7c`), PEM or DER encoded.
+    Pkcs7,
+}
+
+impl CertFormat {
+    /// Guesses the input's format and reports whether the guess is confident.
+    ///
+    /// PEM is confident when a full armor line matched. DER is confident only
+    /// when the input is exactly one well-formed DER SEQUENCE; anything else
+    /// is reported as a low-confidence DER fallback, so callers can warn.
+    pub fn guess(bytes: &[u8]) -> (CertFormat, bool) {
+        match identify_format(bytes) {
+            CertFormat::Pem => (CertFormat::Pem, true),
+            CertFormat::Pkcs7 => (CertFormat::Pkcs7, true),
+            CertFormat::Der => {
+                let confident = matches!(
+                    read_tlv(bytes),
+                    Some((tlv, rest)) if tlv.tag == TAG_SEQUENCE && rest.is_empty()
+                );
+                (CertFormat::Der, confident)
+            }
+        }
+    }
+}
+
+/// Reports whether DER input is a ContentInfo carrying PKCS#7 signedData.
+fn is_der_pkcs7(bytes: &[u8]) -> bool {
+    read_tlv(bytes)
+        .filter(|(outer, _)| outer.tag == TAG_SEQUENCE)
+        .and_then(|(outer, _)| read_tlv(outer.value))
+        .map(|(content_type, _)| {
+            content_type.tag == TAG_OID && content_type.value == OID_PKCS7_SIGNED_DATA
+        })
+        .unwrap_or(false)
+}
+
+/// Identifies whether the input is a PEM certificate, a PKCS#7 bundle, or DER.
+/// Input opening with a recognized PEM armor line is PEM (or PKCS#7 for the
+/// `PKCS7` label), DER carrying the signedData content type is PKCS#7, and
+/// anything else is DER.
+pub fn identify_format(bytes: &[u8]) -> CertFormat {
+    let trimmed = bytes.trim_ascii_start();
+    if trimmed.starts_with(PEM_CERT_HEADER) || trimmed.starts_with(PEM_TRUSTED_CERT_HEADER) {
+        CertFormat::Pem
+    } else if trimmed.starts_with(PEM_PKCS7_HEADER) || is_der_pkcs7(bytes) {
+        CertFormat::Pkcs7
+    } else {
+        CertFormat::Der
+    }
+}
+
+/// Returns the label of the input's first PEM block (e.g., `CERTIFICATE` or
+/// `PKCS7`) without decoding it, or `None` if the input does not open with
+/// a `-----BEGIN <label>-----` line.
+pub fn pem_label(bytes: &[u8]) -> Option<String> {
+    let rest = bytes.trim_ascii_start().strip_prefix(b"-----BEGIN ")?;
+    let end = rest.windows(5).position(|w| w == b"-----")?;
+    let label = std::str::from_utf8(&rest[..end]).ok()?;
+    if label.is_empty() || label.contains(['\r', '\n']) {
+        return None;
+    }
+    Some(label.to_string())
+}
+
+/// Unpacks the certificates carried by a PKCS#7 bundle, PEM or DER encoded.
+///
+/// # Errors
+/// Returns `CertFormatError::EmptyInput` for empty input, or an error if the
+/// bundle cannot be parsed or carries no certificates.
+pub fn stack_from_pkcs7(bytes: &[u8]) -> Result<Vec<Certificate>, Box<dyn std::error::Error>> {
+    if bytes.trim_ascii().is_empty() {
+        return Err(CertFormatError::EmptyInput.into());
+    }
+    let pkcs7 = if bytes.trim_ascii_start().starts_with(PEM_PKCS7_HEADER) {
+        Pkcs7::from_pem(&crlf_to_lf(bytes))?
+    } else {
+        Pkcs7::from_der(bytes)?
+    };
+    let certs = pkcs7
+        .signed()
+        .and_then(|signed| signed.certificates())
+        .ok_or("PKCS#7 bundle does not carry any certificates")?;
+    Ok(certs.iter().map(|x509| Certificate::from(x509.to_owned())).collect())
+}
+
+/// Decodes a `TRUSTED CERTIFICATE` PEM block, discarding the trust settings
+/// that follow the certificate DER.
+fn from_trusted_pem(pem: &[u8]) -> Result<Certificate, Box<dyn std::error::Error>> {
+    let body = &pem[PEM_TRUSTED_CERT_HEADER.len()..];
+    let end = body
+        .windows(PEM_TRUSTED_CERT_FOOTER.len())
+        .position(|w| w == PEM_TRUSTED_CERT_FOOTER)
+        .ok_or("TRUSTED CERTIFICATE block is missing its END line")?;
+    let base64: String = String::from_utf8(body[..end].to_vec())?
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    let der_bytes = openssl::base64::decode_block(&base64)?;
+
+    // The certificate is the first DER element; the auxiliary trust data follows it.
+    let (_, rest) = read_tlv(&der_bytes).ok_or("Malformed TRUSTED CERTIFICATE DER")?;
+    let cert_len = der_bytes.len() - rest.len();
+    Ok(Certificate::from_der(&der_bytes[..cert_len])?)
+}
+
+/// Converts CRLF line endings (as found in Windows-origin files) to LF.
+fn crlf_to_lf(pem: &[u8]) -> Cow<'_, [u8]> {
+    if !pem.contains(&b'\r') {
+        return Cow::Borrowed(pem);
+    }
+    let mut out = Vec::with_capacity(pem.len());
+    let mut iter = pem.iter().peekable();
+    while let Some(&byte) = iter.next() {
+        if byte == b'\r' && iter.peek() == Some(&&b'\n') {
+            continue;
+        }
+        out.push(byte);
+    }
+    Cow::Owned(out)
+}
+
+/// Parses a PEM-encoded certificate.
+/// Both `CERTIFICATE` and `TRUSTED CERTIFICATE` blocks are accepted, with
+/// either LF or CRLF line endings.
+///
+/// A returned certificate is treated as success even if OpenSSL's error queue
+/// is non-empty. The queue is cleared only when it holds nothing but the
+/// benign PEM "no start line" diagnostic; any other entries are put back so
+/// they are not silently lost.
+///
+/// # Errors
+/// Returns `CertFormatError::EmptyInput` for empty input, or an error if the
+/// bytes are not a valid PEM certificate.
+pub fn from_pem(pem: &[u8]) -> Result<Certificate, Box<dyn std::error::Error>> {
+    let pem = crlf_to_lf(pem);
+    let trimmed = pem.trim_ascii();
+    if trimmed.is_empty() {
+        return Err(CertFormatError::EmptyInput.into());
+    }
+    if trimmed.starts_with(PEM_TRUSTED_CERT_HEADER) {
+        return from_trusted_pem(trimmed);
+    }
+    let x509 = X509::from_pem(trimmed)?;
+    let pending = ErrorStack::get();
+    if !pending.errors().iter().all(is_benign_pem_diagnostic) {
+        for error in pending.errors() {
+            error.put();
+        }
+    }
+    Ok(Certificate::from(x509))
+}
+
+/// OpenSSL library code for the PEM routines (`ERR_LIB_PEM`).
+const ERR_LIB_PEM: i32 = 9;
+/// Reason code OpenSSL queues when a PEM scan finds no block (`PEM_R_NO_START_LINE`).
+const PEM_R_NO_START_LINE: i32 = 108;
+
+/// Returns true if `error` is the PEM "no start line" diagnostic, which OpenSSL
+/// leaves behind when a scan runs past the last block and which does not
+/// indicate a parse failure.
+fn is_benign_pem_diagnostic(error: &openssl::error::Error) -> bool {
+    error.library_code() == ERR_LIB_PEM && error.reason_code() == PEM_R_NO_START_LINE
+}
+
+/// Parses a DER-encoded certificate.
+///
+/// # Errors
+/// Returns `CertFormatError::EmptyInput` for empty input, or an error if the
+/// bytes are not a valid DER certificate.
+pub fn from_der(der: &[u8]) -> Result<Certificate, Box<dyn std::error::Error>> {
+    if der.is_empty() {
+        return Err(CertFormatError::EmptyInput.into());
+    }
+    Ok(Certificate::from_der(der)?)
+}
+
+/// Reports whether `der` is a certificate in canonical DER, for strict callers
+/// that must not accept BER encodings OpenSSL would otherwise tolerate.
+///
+/// The input must be exactly one element, strictly DER-encoded throughout,
+/// and byte-identical to OpenSSL's re-encoding of the parsed certificate.
+///
+/// # Errors
+/// Returns an error if the bytes are not a certificate at all.
+pub fn is_canonical_der(der: &[u8]) -> Result<bool, Box<dyn std::error::Error>> {
+    let cert = from_der(der)?;
+    let single = matches!(read_tlv(der), Some((_, rest)) if rest.is_empty());
+    Ok(single && is_strict_der(der) && cert.to_der()? == der)
+}
+
+/// Parses a certificate from either PEM or DER bytes, as detected by `identify_format`.
+///
+/// # Errors
+/// Returns `CertFormatError::EmptyInput` for empty input, or an error if the
+/// bytes do not contain a valid certificate.
+pub fn from_bytes(bytes: &[u8]) -> Result<Certificate, Box<dyn std::error::Error>> {
+    if bytes.trim_ascii().is_empty() {
+        return Err(CertFormatError::EmptyInput.into());
+    }
+    match identify_format(bytes) {
+        CertFormat::Pem => from_pem(bytes),
+        CertFormat::Der => from_der(bytes),
+        CertFormat::Pkcs7 => {
+            Err("Input is a PKCS#7 bundle; use stack_from_pkcs7 to unpack it".into())
+        }
+    }
+}
+
+/// Parses a certificate like `from_bytes`, but refuses to guess: only input
+/// with a PEM armor line or a single well-formed DER SEQUENCE is accepted.
+///
+/// # Errors
+/// Returns `CertFormatError::AmbiguousInput` for input whose format is not
+/// certain, or an error if the bytes are not a valid certificate.
+pub fn from_bytes_strict(bytes: &[u8]) -> Result<Certificate, Box<dyn std::error::Error>> {
+    if bytes.trim_ascii().is_empty() {
+        return Err(CertFormatError::EmptyInput.into());
+    }
+    match CertFormat::guess(bytes) {
+        (_, false) => Err(CertFormatError::AmbiguousInput.into()),
+        (_, true) => from_bytes(bytes),
+    }
+}
+
+/// Raised by `load_dir` when some files in the directory are not certificates.
+/// Carries the certificates that did load, so callers can still use them.
+#[derive(Debug, Snafu)]
+#[snafu(display("Failed to load {} certificate file(s): {}", failures.len(), failures
+    .iter()
+    .map(|(path, reason)| format!("{}: {}", path.display(), reason))
+    .collect::<Vec<_>>()
+    .join("; ")))]
+pub struct LoadDirError {
+    /// Certificates parsed from the files that loaded successfully.
+    pub loaded: Vec<Certificate>,
+    /// Each file that failed, with the reason.
+    pub failures: Vec<(PathBuf, String)>,
+}
+
+/// Loads every regular file in a directory as a PEM or DER certificate.
+///
+/// # Arguments
+/// * `dir` - The directory to scan (not recursively).
+///
+/// # Errors
+/// Returns an error if the directory cannot be read, or a `LoadDirError`
+/// listing the files that failed to parse alongside those that did.
+pub fn load_dir<P: AsRef<Path>>(dir: P) -> Result<Vec<Certificate>, Box<dyn std::error::Error>> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            paths.push(entry.path());
+        }
+    }
+    // Sort for a deterministic result regardless of directory order.
+    paths.sort();
+
+    let mut loaded = Vec::new();
+    let mut failures = Vec::new();
+    for path in paths {
+        match std::fs::read(&path).map_err(|e| e.into()).and_then(|bytes| from_bytes(&bytes)) {
+            Ok(cert) => loaded.push(cert),
+            Err(err) => failures.push((path, err.to_string())),
+        }
+    }
+    if failures.is_empty() {
+        Ok(loaded)
+    } else {
+        Err(LoadDirError { loaded, failures }.into())
+    }
+}
+
+/// Strips ASCII whitespace (as left by copy-pasting) and validates that the
+/// remaining input is UTF-8 text.
+fn compact_text(input: &[u8], encoding: &'static str) -> Result<String, CertFormatError> {
+    let compact: Vec<u8> = input
+        .iter()
+        .copied()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+    if compact.is_empty() {
+        return Err(CertFormatError::EmptyInput);
+    }
+    String::from_utf8(compact).map_err(|err| CertFormatError::InvalidEncoding {
+        encoding,
+        reason: err.to_string(),
+    })
+}
+
+/// Parses a certificate from hex text encoding its PEM or DER bytes.
+/// Embedded whitespace and newlines are ignored.
+///
+/// # Errors
+/// Returns `CertFormatError::InvalidEncoding` if the input is not valid hex,
+/// or an error if the decoded bytes are not a valid certificate.
+pub fn from_hex(input: &[u8]) -> Result<Certificate, Box<dyn std::error::Error>> {
+    let text = compact_text(input, "hex")?;
+    let bytes = hex::decode(&text).map_err(|err| CertFormatError::InvalidEncoding {
+        encoding: "hex",
+        reason: err.to_string(),
+    })?;
+    from_bytes(&bytes)
+}
+
+/// Parses a certificate from base64 text encoding its PEM or DER bytes.
+/// Embedded whitespace and newlines are ignored.
+///
+/// # Errors
+/// Returns `CertFormatError::InvalidEncoding` if the input is not valid
+/// base64, or an error if the decoded bytes are not a valid certificate.
+pub fn from_base64(input: &[u8]) -> Result<Certificate, Box<dyn std::error::Error>> {
+    let text = compact_text(input, "base64")?;
+    let bytes = openssl::base64::decode_block(&text).map_err(|err| CertFormatError::InvalidEncoding {
+        encoding: "base64",
+        reason: err.to_string(),
+    })?;
+    from_bytes(&bytes)
+}
+
+/// Reads a certificate from any byte source, such as a network stream or an
+/// archive entry, without requiring it to exist on disk.
+///
+/// # Arguments
+/// * `reader` - The source to read the whole certificate from.
+///
+/// # Errors
+/// Returns an error if reading fails or the bytes are not a valid certificate.
+pub fn from_reader<R: Read>(mut reader: R) -> Result<Certificate, Box<dyn std::error::Error>> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    from_bytes(&buf)
+}
+
+/// Re-encodes the certificate as canonical PEM by round-tripping through DER.
+///
+/// Certificates that differ only in PEM line wrapping, line endings, or
+/// surrounding whitespace normalize to identical bytes (64-column base64 body,
+/// LF line endings).
+///
+/// # Errors
+/// Returns an error if the certificate cannot be re-encoded.
+pub fn normalized_pem(cert: &Certificate) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let der_bytes = cert.to_der()?;
+    Ok(X509::from_der(&der_bytes)?.to_pem()?)
+}
+
+/// Renders the certificate in the human-readable form printed by
+/// `openssl x509 -text`, for diagnosing certificates that fail checks.
+///
+/// # Errors
+/// Returns an error if OpenSSL cannot print the certificate.
+pub fn to_text(cert: &Certificate) -> Result<String, Box<dyn std::error::Error>> {
+    let x509: &X509 = cert.into();
+    Ok(String::from_utf8_lossy(&x509.to_text()?).into_owned())
+}
+
+/// Verifies a detached signature over `message` with the certificate's public key,
+/// e.g. a manifest endorsed by the VCEK.
+///
+/// # Arguments
+/// * `cert` - The certificate whose public key made the signature.
+/// * `message` - The signed message.
+/// * `signature` - The signature, in the key type's standard encoding
+///   (DER for ECDSA, raw for RSA).
+/// * `digest` - The digest the signer hashed the message with.
+///
+/// # Returns
+/// `true` if the signature is valid, `false` if it is not.
+///
+/// # Errors
+/// Returns an error if the public key cannot be extracted or the
+/// verification cannot be performed.
+pub fn verify_signature(
+    cert: &Certificate,
+    message: &[u8],
+    signature: &[u8],
+    digest: MessageDigest,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let x509: &X509 = cert.into();
+    let public_key = x509.public_key()?;
+    let mut verifier = Verifier::new(digest, &public_key)?;
+    verifier.update(message)?;
+    Ok(verifier.verify(signature)?)
+}
+
+/// Converts an ASN.1 time into seconds since the Unix epoch.
+fn asn1_to_unix(time: &Asn1TimeRef) -> Result<i64, Box<dyn std::error::Error>> {
+    let epoch = Asn1Time::from_unix(0)?;
+    let diff = epoch.diff(time)?;
+    Ok(i64::from(diff.days) * 86_400 + i64::from(diff.secs))
+}
+
+/// Returns the certificate's `notBefore` time as seconds since the Unix epoch.
+///
+/// # Errors
+/// Returns an error if the validity time cannot be converted.
+pub fn not_before_unix(cert: &Certificate) -> Result<i64, Box<dyn std::error::Error>> {
+    let x509: &X509 = cert.into();
+    asn1_to_unix(x509.not_before())
+}
+
+/// Returns the certificate's `notAfter` time as seconds since the Unix epoch.
+///
+/// # Arguments
+/// * `cert` - The certificate to inspect (e.g., a VCEK fetched from KDS).
+///
+/// # Errors
+/// Returns an error if the validity time cannot be converted.
+pub fn not_after_unix(cert: &Certificate) -> Result<i64, Box<dyn std::error::Error>> {
+    let x509: &X509 = cert.into();
+    asn1_to_unix(x509.not_after())
+}
+
+/// Returns the number of seconds from `now` until the certificate expires.
+/// The result is negative if the certificate has already expired.
+///
+/// # Arguments
+/// * `cert` - The certificate to inspect.
+/// * `now` - The reference time, in seconds since the Unix epoch.
+///
+/// # Errors
+/// Returns an error if the validity time cannot be converted.
+pub fn seconds_until_expiry(cert: &Certificate, now: i64) -> Result<i64, Box<dyn std::error::Error>> {
+    Ok(not_after_unix(cert)? - now)
+}
+
+/// Returns the number of seconds until the certificate expires, as measured
+/// by the given clock.
+///
+/// # Errors
+/// Returns an error if the validity time cannot be converted.
+pub fn seconds_until_expiry_with(
+    cert: &Certificate,
+    clock: &dyn Clock,
+) -> Result<i64, Box<dyn std::error::Error>> {
+    seconds_until_expiry(cert, clock.now_unix())
+}
+
+/// Reports whether the certificate expires within `window` of `now`, so a
+/// scheduler can refresh it before it lapses. Already-expired certificates
+/// count as expiring.
+///
+/// # Arguments
+/// * `cert` - The certificate to inspect.
+/// * `window` - How far ahead of `now` to look.
+/// * `now` - The reference time, in seconds since the Unix epoch.
+///
+/// # Errors
+/// Returns an error if the validity time cannot be converted.
+pub fn expires_within(
+    cert: &Certificate,
+    window: Duration,
+    now: i64,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let window = i64::try_from(window.as_secs()).unwrap_or(i64::MAX);
+    Ok(seconds_until_expiry(cert, now)? <= window)
+}
+
+/// Reports whether `now` falls within the certificate's validity period.
+///
+/// # Arguments
+/// * `cert` - The certificate to inspect.
+/// * `now` - The reference time, in seconds since the Unix epoch.
+///
+/// # Errors
+/// Returns an error if the validity times cannot be converted.
+pub fn is_valid_at(cert: &Certificate, now: i64) -> Result<bool, Box<dyn std::error::Error>> {
+    Ok(not_before_unix(cert)? <= now && now <= not_after_unix(cert)?)
+}
+
+/// Checks that every certificate in the chain is within its validity period.
+///
+/// # Arguments
+/// * `chain` - The chain to check.
+/// * `clock` - The clock to read "now" from; defaults to the system clock.
+///
+/// # Errors
+/// Returns an error naming the first certificate that is not yet valid or has expired.
+pub fn check_chain_validity(
+    chain: &Chain,
+    clock: Option<&dyn Clock>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let now = clock.unwrap_or(&SystemClock).now_unix();
+    for (name, cert) in [("ARK", &chain.ca.ark), ("ASK", &chain.ca.ask), ("VCEK", &chain.vek)] {
+        if !is_valid_at(cert, now)? {
+            return Err(format!("{name} is outside its validity period").into());
+        }
+    }
+    Ok(())
+}
+
+/// Verifies the chain's signatures and checks every certificate's validity
+/// period at `now`, tolerating up to `skew` of clock error in either direction.
+///
+/// Signature-only verification (`Verifiable::verify`) is unaffected; use this
+/// when validity should also be enforced on hosts whose clock may drift.
+///
+/// # Errors
+/// Returns an error if a signature does not verify or a certificate is
+/// outside its validity period by more than `skew`.
+pub fn verify_chain_with_time(
+    chain: &Chain,
+    now: &Asn1TimeRef,
+    skew: Duration,
+) -> Result<(), Box<dyn std::error::Error>> {
+    chain.verify()?;
+    let now = asn1_to_unix(now)?;
+    let skew = i64::try_from(skew.as_secs()).unwrap_or(i64::MAX);
+    for (name, cert) in [("ARK", &chain.ca.ark), ("ASK", &chain.ca.ask), ("VCEK", &chain.vek)] {
+        let earliest = not_before_unix(cert)?.saturating_sub(skew);
+        let latest = not_after_unix(cert)?.saturating_add(skew);
+        if now < earliest || now > latest {
+            return Err(format!("{name} is outside its validity period, even allowing for clock skew").into());
+        }
+    }
+    Ok(())
+}
+
+/// OID of the BasicConstraints extension.
+const OID_BASIC_CONSTRAINTS: &str = "2.5.29.19";
+/// OID of the KeyUsage extension.
+const OID_KEY_USAGE: &str = "2.5.29.15";
+/// OID of the SubjectKeyIdentifier extension.
+const OID_SUBJECT_KEY_ID: &str = "2.5.29.14";
+/// OID of the AuthorityKeyIdentifier extension.
+const OID_AUTHORITY_KEY_ID: &str = "2.5.29.35";
+
+/// AMD's private enterprise arc, under which the VCEK extensions live.
+const AMD_OID_ARC: &str = "1.3.6.1.4.1.3704.";
+
+/// An X.509v3 extension as found in the certificate's TBS section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertExtension {
+    /// The extension's OBJECT IDENTIFIER in dotted form (e.g., `"2.5.29.15"`).
+    pub oid: String,
+    /// Whether the extension is marked critical.
+    pub critical: bool,
+    /// The DER-encoded extension value (the OCTET STRING contents).
+    pub value: Vec<u8>,
+}
+
+/// The usages asserted by a certificate's KeyUsage extension.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyUsageFlags {
+    pub digital_signature: bool,
+    pub non_repudiation: bool,
+    pub key_encipherment: bool,
+    pub data_encipherment: bool,
+    pub key_agreement: bool,
+    pub key_cert_sign: bool,
+    pub crl_sign: bool,
+    pub encipher_only: bool,
+    pub decipher_only: bool,
+}
+
+/// Returns all X.509v3 extensions carried by the certificate.
+///
+/// # Arguments
+/// * `cert` - The certificate to inspect.
+///
+/// # Errors
+/// Returns an error if the certificate cannot be re-encoded or its DER is malformed.
+pub fn extensions(cert: &Certificate) -> Result<Vec<CertExtension>, Box<dyn std::error::Error>> {
+    let x509: &X509 = cert.into();
+    let der_bytes = x509.to_der()?;
+    let malformed = || "Malformed certificate DER";
+
+    // Step 1: Unwrap the outer Certificate SEQUENCE down to the TBSCertificate.
+    let (cert_seq, _) = read_tlv(&der_bytes).ok_or_else(malformed)?;
+    let (tbs, _) = read_tlv(cert_seq.value).ok_or_else(malformed)?;
+    let tbs_fields = read_all(tbs.value).ok_or_else(malformed)?;
+
+    // Step 2: Locate the explicitly tagged [3] extensions field, if any.
+    let ext_field = match tbs_fields.iter().find(|field| field.tag == 0xa3) {
+        Some(field) => field,
+        None => return Ok(Vec::new()),
+    };
+    let (ext_seq, _) = read_tlv(ext_field.value).ok_or_else(malformed)?;
+
+    // Step 3: Decode each Extension ::= SEQUENCE { extnID, critical, extnValue }.
+    let mut result = Vec::new();
+    for ext in read_all(ext_seq.value).ok_or_else(malformed)? {
+        let parts = read_all(ext.value).ok_or_else(malformed)?;
+        let oid = parts
+            .iter()
+            .find(|p| p.tag == TAG_OID)
+            .ok_or_else(malformed)?;
+        let critical = parts
+            .iter()
+            .find(|p| p.tag == TAG_BOOLEAN)
+            .map(|p| p.value.first().copied().unwrap_or(0) != 0)
+            .unwrap_or(false);
+        let value = parts
+            .iter()
+            .find(|p| p.tag == TAG_OCTET_STRING)
+            .ok_or_else(malformed)?;
+        result.push(CertExtension {
+            oid: decode_oid(oid.value).ok_or("Malformed extension OID")?,
+            critical,
+            value: value.value.to_vec(),
+        });
+    }
+    Ok(result)
+}
+
+/// Returns the extension with the given dotted OID, if present.
+pub fn find_extension(
+    cert: &Certificate,
+    oid: &str,
+) -> Result<Option<CertExtension>, Box<dyn std::error::Error>> {
+    Ok(extensions(cert)?.into_iter().find(|ext| ext.oid == oid))
+}
+
+/// Returns the raw value of an AMD extension, identified by its dotted OID,
+/// so fields not modelled by this crate can still be decoded by callers.
+///
+/// # Arguments
+/// * `cert` - The certificate to inspect (typically a VCEK).
+/// * `oid` - A dotted OID under AMD's arc, e.g. `"1.3.6.1.4.1.3704.1.4"` (HWID).
+///
+/// # Returns
+/// The DER-encoded extension value, or `None` if the certificate lacks it.
+///
+/// # Errors
+/// Returns an error if the OID is invalid or outside AMD's arc, or the
+/// certificate is malformed.
+pub fn amd_extension_raw(
+    cert: &Certificate,
+    oid: &str,
+) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    if !oid.starts_with(AMD_OID_ARC) {
+        return Err(format!("OID {oid} is not under AMD's arc {AMD_OID_ARC}").into());
+    }
+    encode_oid(oid).ok_or_else(|| format!("Invalid OID: {oid}"))?;
+    Ok(find_extension(cert, oid)?.map(|ext| ext.value))
+}
+
+/// AMD VCEK extension OIDs carrying the security patch level of each TCB component.
+pub(crate) const OID_BL_SPL: &str = "1.3.6.1.4.1.3704.1.3.1";
+pub(crate) const OID_TEE_SPL: &str = "1.3.6.1.4.1.3704.1.3.2";
+pub(crate) const OID_SNP_SPL: &str = "1.3.6.1.4.1.3704.1.3.3";
+pub(crate) const OID_UCODE_SPL: &str = "1.3.6.1.4.1.3704.1.3.8";
+
+/// Decodes one SPL extension, a DER INTEGER holding a single-byte SVN.
+/// DER requires the minimal encoding, so the leading zero byte is accepted
+/// only when the SVN's top bit is set and would otherwise read as negative.
+fn spl_extension(cert: &Certificate, oid: &str) -> Result<u8, Box<dyn std::error::Error>> {
+    let value = amd_extension_raw(cert, oid)?.ok_or_else(|| format!("VCEK is missing SPL extension {oid}"))?;
+    let (tlv, _) = read_tlv(&value)
+        .filter(|(tlv, rest)| tlv.tag == TAG_INTEGER && rest.is_empty())
+        .ok_or_else(|| format!("SPL extension {oid} is not an INTEGER"))?;
+    match tlv.value {
+        [svn] if *svn < 0x80 => Ok(*svn),
+        [0, svn] if *svn >= 0x80 => Ok(*svn),
+        [_] | [0, _] => Err(format!("SPL extension {oid} is not a minimally encoded non-negative INTEGER").into()),
+        _ => Err(format!("SPL extension {oid} does not fit in a byte").into()),
+    }
+}
+
+/// Returns the TCB a VCEK was issued for, assembled from its SPL extensions.
+///
+/// # Errors
+/// Returns an error if any of the four SPL extensions is missing or malformed.
+pub fn vcek_tcb(cert: &Certificate) -> Result<TcbVersion, Box<dyn std::error::Error>> {
+    Ok(TcbVersion {
+        bootloader: spl_extension(cert, OID_BL_SPL)?,
+        tee: spl_extension(cert, OID_TEE_SPL)?,
+        snp: spl_extension(cert, OID_SNP_SPL)?,
+        microcode: spl_extension(cert, OID_UCODE_SPL)?,
+        _reserved: [0; 4],
+    })
+}
+
+/// Checks that the VCEK was issued for the TCB the report claims, so a
+/// report cannot be paired with a VCEK for a different firmware level.
+///
+/// # Errors
+/// Returns an error if the VCEK's TCB cannot be read or differs from the
+/// report's reported TCB.
+pub fn check_tcb_consistency(
+    vcek: &Certificate,
+    report: &AttestationReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let issued = vcek_tcb(vcek)?;
+    let claimed = reported_tcb(report);
+    let components = |tcb: &TcbVersion| (tcb.bootloader, tcb.tee, tcb.snp, tcb.microcode);
+    if components(&issued) != components(&claimed) {
+        return Err(format!(
+            "VCEK was issued for TCB {:?} but the report claims {:?}",
+            issued, claimed
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Returns the certificate's Subject Key Identifier, if it carries one.
+///
+/// # Errors
+/// Returns an error if the certificate or the extension value is malformed.
+pub fn subject_key_id(cert: &Certificate) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    let ext = match find_extension(cert, OID_SUBJECT_KEY_ID)? {
+        Some(ext) => ext,
+        None => return Ok(None),
+    };
+    // SubjectKeyIdentifier ::= OCTET STRING
+    let (key_id, _) = read_tlv(&ext.value)
+        .filter(|(tlv, _)| tlv.tag == TAG_OCTET_STRING)
+        .ok_or("Malformed SubjectKeyIdentifier extension")?;
+    Ok(Some(key_id.value.to_vec()))
+}
+
+/// Returns the key identifier from the certificate's Authority Key Identifier,
+/// if it carries one.
+///
+/// # Errors
+/// Returns an error if the certificate or the extension value is malformed.
+pub fn authority_key_id(cert: &Certificate) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    let ext = match find_extension(cert, OID_AUTHORITY_KEY_ID)? {
+        Some(ext) => ext,
+        None => return Ok(None),
+    };
+    // AuthorityKeyIdentifier ::= SEQUENCE { keyIdentifier [0] IMPLICIT OCTET STRING OPTIONAL, ... }
+    let (seq, _) = read_tlv(&ext.value).ok_or("Malformed AuthorityKeyIdentifier extension")?;
+    let fields = read_all(seq.value).ok_or("Malformed AuthorityKeyIdentifier extension")?;
+    Ok(fields
+        .iter()
+        .find(|field| field.tag == 0x80)
+        .map(|field| field.value.to_vec()))
+}
+
+/// Reports whether the certificate asserts `CA:TRUE` in its BasicConstraints.
+/// A certificate without the extension is not a CA.
+///
+/// # Errors
+/// Returns an error if the certificate or the extension value is malformed.
+pub fn is_ca(cert: &Certificate) -> Result<bool, Box<dyn std::error::Error>> {
+    let ext = match find_extension(cert, OID_BASIC_CONSTRAINTS)? {
+        Some(ext) => ext,
+        None => return Ok(false),
+    };
+    // BasicConstraints ::= SEQUENCE { cA BOOLEAN DEFAULT FALSE, pathLenConstraint INTEGER OPTIONAL }
+    let (seq, _) = read_tlv(&ext.value).ok_or("Malformed BasicConstraints extension")?;
+    let fields = read_all(seq.value).ok_or("Malformed BasicConstraints extension")?;
+    Ok(fields
+        .first()
+        .filter(|field| field.tag == TAG_BOOLEAN)
+        .map(|field| field.value.first().copied().unwrap_or(0) != 0)
+        .unwrap_or(false))
+}
+
+/// Returns the usages asserted by the certificate's KeyUsage extension.
+/// A certificate without the extension reports no usages at all, so it is
+/// never treated as permitted to sign other certificates.
+///
+/// # Errors
+/// Returns an error if the certificate or the extension value is malformed.
+pub fn key_usage(cert: &Certificate) -> Result<KeyUsageFlags, Box<dyn std::error::Error>> {
+    let ext = match find_extension(cert, OID_KEY_USAGE)? {
+        Some(ext) => ext,
+        None => return Ok(KeyUsageFlags::default()),
+    };
+    // KeyUsage ::= BIT STRING; the first content byte counts the unused bits.
+    let (bits, _) = read_tlv(&ext.value)
+        .filter(|(tlv, _)| tlv.tag == TAG_BIT_STRING && !tlv.value.is_empty())
+        .ok_or("Malformed KeyUsage extension")?;
+    let bit = |n: usize| {
+        bits.value
+            .get(1 + n / 8)
+            .map(|byte| byte & (0x80 >> (n % 8)) != 0)
+            .unwrap_or(false)
+    };
+    Ok(KeyUsageFlags {
+        digital_signature: bit(0),
+        non_repudiation: bit(1),
+        key_encipherment: bit(2),
+        data_encipherment: bit(3),
+        key_agreement: bit(4),
+        key_cert_sign: bit(5),
+        crl_sign: bit(6),
+        encipher_only: bit(7),
+        decipher_only: bit(8),
+    })
+}
+
+/// A certificate's public key is not of the type its role requires.
+#[derive(Debug, Snafu)]
+#[snafu(display("Unsupported {algorithm} key: expected {expected}"))]
+pub struct UnsupportedKeyType {
+    /// The algorithm (and curve, for EC keys) the certificate actually carries.
+    pub algorithm: String,
+    /// The key type the certificate's role requires.
+    pub expected: &'static str,
+}
+
+/// Returns the certificate's public key, checking that its type fits the
+/// certificate's role: RSA for the ARK and ASK, EC P-384 for the VCEK.
+///
+/// # Errors
+/// Returns `UnsupportedKeyType` naming the actual algorithm if it does not
+/// fit, or an error if the key cannot be extracted.
+pub fn checked_public_key(cert: &Certificate) -> Result<PKey<Public>, Box<dyn std::error::Error>> {
+    let key = cert.public_key()?;
+    let algorithm = Nid::from_raw(key.id().as_raw())
+        .short_name()
+        .unwrap_or("unknown")
+        .to_string();
+    if is_ca(cert)? {
+        if key.id() != Id::RSA && key.id() != Id::RSA_PSS {
+            return Err(UnsupportedKeyType { algorithm, expected: "RSA" }.into());
+        }
+        return Ok(key);
+    }
+    if key.id() != Id::EC {
+        return Err(UnsupportedKeyType { algorithm, expected: "EC P-384" }.into());
+    }
+    let curve = key.ec_key()?.group().curve_name();
+    if curve != Some(Nid::SECP384R1) {
+        let curve = curve.and_then(|nid| nid.short_name().ok()).unwrap_or("unknown curve");
+        return Err(UnsupportedKeyType {
+            algorithm: format!("{algorithm} {curve}"),
+            expected: "EC P-384",
+        }
+        .into());
+    }
+    Ok(key)
+}
+
+/// Checks that the ARK and ASK are CAs permitted to sign certificates.
+///
+/// # Errors
+/// Returns an error naming the first certificate whose role is wrong.
+pub fn check_ca_roles(ca: &ca::Chain) -> Result<(), Box<dyn std::error::Error>> {
+    if !is_ca(&ca.ark)? {
+        return Err("ARK does not assert CA:TRUE".into());
+    }
+    if !key_usage(&ca.ark)?.key_cert_sign {
+        return Err("ARK KeyUsage does not permit keyCertSign".into());
+    }
+    if !is_ca(&ca.ask)? {
+        return Err("ASK does not assert CA:TRUE".into());
+    }
+    if !key_usage(&ca.ask)?.key_cert_sign {
+        return Err("ASK KeyUsage does not permit keyCertSign".into());
+    }
+    Ok(())
+}
+
+/// The zero-indexed X.509 version number of a v3 certificate.
+pub const X509_V3: i32 = 2;
+
+/// Returns the certificate's zero-indexed X.509 version (`X509_V3` for v3).
+pub fn x509_version(cert: &Certificate) -> i32 {
+    let x509: &X509 = cert.into();
+    x509.version()
+}
+
+/// A certificate's issuer name does not match the subject name of the
+/// certificate that is supposed to have issued it.
+#[derive(Debug, Snafu)]
+#[snafu(display("{subject} issuer name does not match the {issuer} subject name"))]
+pub struct NameMismatch {
+    /// Role of the certificate expected to be the issuer.
+    pub issuer: &'static str,
+    /// Role of the certificate whose issuer name is wrong.
+    pub subject: &'static str,
+}
+
+/// Checks that each certificate names its expected issuer: the ARK itself,
+/// the ARK for the ASK, and the ASK for the VCEK. This catches a certificate
+/// paired with a differently named signer even if the signatures line up.
+///
+/// # Errors
+/// Returns `NameMismatch` identifying the first mismatched pair, or an error
+/// if a name cannot be encoded.
+pub fn check_chain_names(chain: &Chain) -> Result<(), Box<dyn std::error::Error>> {
+    let links = [
+        ("ARK", &chain.ca.ark, "ARK", &chain.ca.ark),
+        ("ARK", &chain.ca.ark, "ASK", &chain.ca.ask),
+        ("ASK", &chain.ca.ask, "VCEK", &chain.vek),
+    ];
+    for (issuer, issuer_cert, subject, subject_cert) in links {
+        let (issuer_x509, subject_x509): (&X509, &X509) = (issuer_cert.into(), subject_cert.into());
+        if issuer_x509.subject_name().to_der()? != subject_x509.issuer_name().to_der()? {
+            return Err(NameMismatch { issuer, subject }.into());
+        }
+    }
+    Ok(())
+}
+
+/// Why a signer and signee do or do not form a valid pair, as found by
+/// `diagnose_pair`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PairDiagnosis {
+    /// The signee's issuer name equals the signer's subject name.
+    pub issuer_matches_subject: bool,
+    /// The signer is a CA whose KeyUsage permits keyCertSign.
+    pub key_usage_ok: bool,
+    /// The signer's key verifies the signee's signature.
+    pub signature_valid: bool,
+}
+
+impl PairDiagnosis {
+    /// Reports whether every aspect of the pair checks out.
+    pub fn is_valid(&self) -> bool {
+        self.issuer_matches_subject && self.key_usage_ok && self.signature_valid
+    }
+}
+
+/// Examines a signer/signee pair independently along each axis, to explain
+/// why `(signer, signee).verify()` fails: a misnamed issuer, a signer not
+/// permitted to sign certificates, or a genuinely bad signature.
+///
+/// Any aspect that cannot be decoded is reported as failing.
+pub fn diagnose_pair(signer: &Certificate, signee: &Certificate) -> PairDiagnosis {
+    let (signer_x509, signee_x509): (&X509, &X509) = (signer.into(), signee.into());
+    let issuer_matches_subject = matches!(
+        (signer_x509.subject_name().to_der(), signee_x509.issuer_name().to_der()),
+        (Ok(subject), Ok(issuer)) if subject == issuer
+    );
+    let key_usage_ok = is_ca(signer).unwrap_or(false)
+        && key_usage(signer).is_ok_and(|usage| usage.key_cert_sign);
+    PairDiagnosis {
+        issuer_matches_subject,
+        key_usage_ok,
+        signature_valid: (signer, signee).verify().is_ok(),
+    }
+}
+
+/// Checks that each certificate in the chain plays its expected role:
+/// all three must be X.509 v3 (earlier versions cannot carry the
+/// extensions roles are derived from), each must name its expected issuer
+/// (see `check_chain_names`), the ARK and ASK must be CAs permitted to sign
+/// certificates, and the VCEK must not be a CA.
+///
+/// # Errors
+/// Returns an error naming the first certificate whose version, issuer name
+/// or role is wrong.
+pub fn check_chain_roles(chain: &Chain) -> Result<(), Box<dyn std::error::Error>> {
+    for (name, cert) in [("ARK", &chain.ca.ark), ("ASK", &chain.ca.ask), ("VCEK", &chain.vek)] {
+        let version = x509_version(cert);
+        if version != X509_V3 {
+            return Err(format!("{name} is X.509 v{} rather than v3", version + 1).into());
+        }
+    }
+    check_chain_names(chain)?;
+    check_ca_roles(&chain.ca)?;
+    if is_ca(&chain.vek)? {
+        return Err("VCEK must not assert CA:TRUE".into());
+    }
+    Ok(())
+}
+
+/// Validates the CA portion of a chain (ARK and ASK) on its own, so it can be
+/// cached and revalidated independently of the per-chip VCEKs it signs.
+///
+/// Checks the ARK self-signature, the ARK signing the ASK, and both
+/// certificates' CA roles.
+///
+/// # Errors
+/// Returns an error if a signature does not verify or a role is wrong.
+pub fn verify_ca_chain(ca: &ca::Chain) -> Result<(), Box<dyn std::error::Error>> {
+    ca.verify()?;
+    check_ca_roles(ca)
+}
+
+/// No signer was available for a certificate that must be verified.
+#[derive(Debug, Snafu)]
+#[snafu(display("No signer available to verify the certificate"))]
+pub struct MissingSigner;
+
+/// Verifies `signee` against a signer that may be absent, such as the ASK
+/// or ASVK depending on whether a VCEK or VLEK signed the report.
+///
+/// `Verifiable` cannot be implemented for `(Option<&Certificate>, &Certificate)`
+/// outside the `sev` crate, so this is provided as a function instead.
+///
+/// # Errors
+/// Returns `MissingSigner` if `signer` is `None`, or an error if the
+/// signature does not verify.
+pub fn verify_with_optional_signer(
+    signer: Option<&Certificate>,
+    signee: &Certificate,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let signer = signer.ok_or(MissingSigner)?;
+    (signer, signee).verify()?;
+    Ok(())
+}
+
+/// Verifies an ASK and VCEK supplied without their ARK against an ARK the
+/// caller already trusts out-of-band.
+///
+/// The trusted ARK's self-signature is not re-checked; only the ARK→ASK and
+/// ASK→VCEK signatures are verified, followed by the same name and role
+/// checks as a full chain (see `check_chain_roles`).
+///
+/// # Errors
+/// Returns an error if either signature does not verify, or a certificate's
+/// version, issuer name or role is wrong.
+pub fn verify_with_trusted_ark(
+    ask: &Certificate,
+    vcek: &Certificate,
+    trusted_ark: &Certificate,
+) -> Result<(), Box<dyn std::error::Error>> {
+    (trusted_ark, ask).verify()?;
+    (ask, vcek).verify()?;
+    let chain = Chain {
+        ca: ca::Chain {
+            ark: trusted_ark.clone(),
+            ask: ask.clone(),
+        },
+        vek: vcek.clone(),
+    };
+    check_chain_roles(&chain)
+}
+
+/// Dotted OIDs appearing in AMD's RSASSA-PSS signature algorithm parameters.
+const OID_RSASSA_PSS: &str = "1.2.840.113549.1.1.10";
+const OID_MGF1: &str = "1.2.840.113549.1.1.8";
+const OID_SHA384: &str = "2.16.840.1.101.3.4.2.2";
+/// Salt length AMD uses with SHA-384, equal to the digest size.
+const PSS_SALT_LEN: u8 = 48;
+
+/// Returns the dotted OID at the front of an AlgorithmIdentifier SEQUENCE,
+/// along with the remaining parameter elements.
+fn algorithm_identifier<'a>(seq: &Tlv<'a>) -> Option<(String, Vec<Tlv<'a>>)> {
+    if seq.tag != TAG_SEQUENCE {
+        return None;
+    }
+    let mut items = read_all(seq.value)?.into_iter();
+    let oid = items.next().filter(|tlv| tlv.tag == TAG_OID)?;
+    Some((decode_oid(oid.value)?, items.collect()))
+}
+
+/// Checks that the certificate is signed with RSASSA-PSS using exactly the
+/// parameters AMD uses: SHA-384, MGF1 with SHA-384, and a 48-byte salt.
+///
+/// # Errors
+/// Returns an error describing the first parameter that differs, or if the
+/// signature algorithm cannot be decoded.
+pub fn ensure_rsa_pss_params(cert: &Certificate) -> Result<(), Box<dyn std::error::Error>> {
+    let der = cert.to_der()?;
+
+    // Step 1: Locate signatureAlgorithm, the second element of the certificate.
+    let (outer, _) = read_tlv(&der).ok_or("Certificate is not a DER SEQUENCE")?;
+    let items = read_all(outer.value).ok_or("Certificate contents are malformed")?;
+    let (oid, params) = items
+        .get(1)
+        .and_then(algorithm_identifier)
+        .ok_or("Signature algorithm is missing or malformed")?;
+    if oid != OID_RSASSA_PSS {
+        return Err(format!("Certificate is signed with {oid}, not RSASSA-PSS").into());
+    }
+
+    // Step 2: Walk the explicitly tagged RSASSA-PSS-params fields.
+    let params = params
+        .first()
+        .filter(|tlv| tlv.tag == TAG_SEQUENCE)
+        .and_then(|tlv| read_all(tlv.value))
+        .ok_or("RSASSA-PSS parameters are missing or malformed")?;
+    // Each field is an explicit tag wrapping a single element.
+    let field = |tag: u8| {
+        params
+            .iter()
+            .find(|tlv| tlv.tag == tag)
+            .and_then(|tlv| read_tlv(tlv.value))
+            .map(|(inner, _)| inner)
+    };
+
+    let hash = field(0xa0)
+        .and_then(|tlv| algorithm_identifier(&tlv))
+        .map(|(oid, _)| oid)
+        .ok_or("RSASSA-PSS parameters do not name a hash")?;
+    if hash != OID_SHA384 {
+        return Err(format!("RSASSA-PSS hash is {hash}, expected SHA-384").into());
+    }
+
+    let (mgf, mgf_params) = field(0xa1)
+        .and_then(|tlv| algorithm_identifier(&tlv))
+        .ok_or("RSASSA-PSS parameters do not name a mask generation function")?;
+    let mgf_hash = mgf_params
+        .first()
+        .and_then(algorithm_identifier)
+        .map(|(oid, _)| oid);
+    if mgf != OID_MGF1 || mgf_hash.as_deref() != Some(OID_SHA384) {
+        return Err("RSASSA-PSS mask generation is not MGF1 with SHA-384".into());
+    }
+
+    let salt = field(0xa2)
+        .filter(|tlv| tlv.tag == TAG_INTEGER)
+        .map(|tlv| tlv.value);
+    if salt != Some(&[PSS_SALT_LEN][..]) {
+        return Err(format!("RSASSA-PSS salt length is not {PSS_SALT_LEN}").into());
+    }
+    Ok(())
+}
+
+/// Verifies the chain like `Verifiable::verify` and `check_chain_roles`, and
+/// additionally requires every certificate to be signed with AMD's exact
+/// RSASSA-PSS parameters (see `ensure_rsa_pss_params`).
+///
+/// # Errors
+/// Returns an error if a signature, role or signature parameter check fails.
+pub fn verify_chain_strict(chain: &Chain) -> Result<(), Box<dyn std::error::Error>> {
+    chain.verify()?;
+    check_chain_roles(chain)?;
+    for (name, cert) in [("ARK", &chain.ca.ark), ("ASK", &chain.ca.ask), ("VCEK", &chain.vek)] {
+        ensure_rsa_pss_params(cert).map_err(|err| format!("{name}: {err}"))?;
+    }
+    Ok(())
+}
+
+/// A value that has passed verification.
+///
+/// Only this crate's verification functions can construct one, so APIs that
+/// take a `&Verified<Certificate>` cannot be handed an unchecked certificate:
+///
+/// ```compile_fail
+/// use dev_snp_nif::certs::Verified;
+/// use dev_snp_nif::prelude::Certificate;
+///
+/// fn forge(cert: Certificate) -> Verified<Certificate> {
+///     Verified(cert)
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Verified<T>(T);
+
+impl<T> Verified<T> {
+    /// Marks a value as verified; callers must have just verified it.
+    pub(crate) fn new(value: T) -> Self {
+        Verified(value)
+    }
+
+    /// Returns the verified value.
+    pub fn get(&self) -> &T {
+        &self.0
+    }
+
+    /// Unwraps the value, discarding the proof that it was verified.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> std::ops::Deref for Verified<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+/// Consumes the chain, verifies it, and returns the VCEK ready to verify
+/// reports with.
+///
+/// The VCEK is only handed back if the ARK, ASK and VCEK signatures verify
+/// and each certificate plays its expected role.
+///
+/// # Errors
+/// Returns an error, dropping the chain, if any check fails.
+pub fn verify_into_vcek(chain: Chain) -> Result<Verified<Certificate>, Box<dyn std::error::Error>> {
+    (&chain).verify()?;
+    check_chain_roles(&chain)?;
+    Ok(Verified::new(chain.vek))
+}
+
+/// Verifies a report's signature with a VCEK whose chain has already been
+/// verified (see `verify_into_vcek`).
+///
+/// # Errors
+/// Returns an error if the report signature does not verify.
+pub fn verify_report_with_vcek(
+    vcek: &Verified<Certificate>,
+    report: &AttestationReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    (vcek.get(), report).verify()?;
+    Ok(())
+}
+
+/// Returns the certificate's fingerprint: the digest of its DER encoding.
+///
+/// # Errors
+/// Returns an error if the digest cannot be computed.
+pub fn fingerprint(cert: &Certificate, digest: MessageDigest) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let x509: &X509 = cert.into();
+    Ok(x509.digest(digest)?.to_vec())
+}
+
+/// Returns the certificate's SHA-256 fingerprint.
+pub fn sha256_fingerprint(cert: &Certificate) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    fingerprint(cert, MessageDigest::sha256())
+}
+
+/// Returns the certificate's SHA-384 fingerprint, matching the SNP digest size.
+pub fn sha384_fingerprint(cert: &Certificate) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    fingerprint(cert, MessageDigest::sha384())
+}
+
+/// Compares two certificates by their DER encodings in constant time.
+///
+/// # Errors
+/// Returns an error if either certificate cannot be re-encoded.
+pub fn eq_der(a: &Certificate, b: &Certificate) -> Result<bool, Box<dyn std::error::Error>> {
+    Ok(ct_eq(&a.to_der()?, &b.to_der()?))
+}
+
+/// Reports whether two chains hold the same ARK, ASK and VCEK, comparing
+/// each pair by DER, e.g. to skip re-verifying a refetched chain.
+///
+/// # Errors
+/// Returns an error if any certificate cannot be re-encoded.
+pub fn chain_eq_der(a: &Chain, b: &Chain) -> Result<bool, Box<dyn std::error::Error>> {
+    Ok(eq_der(&a.ca.ark, &b.ca.ark)? & eq_der(&a.ca.ask, &b.ca.ask)? & eq_der(&a.vek, &b.vek)?)
+}
+
+/// A certificate paired with its DER encoding, computed on first use and
+/// cached so repeated `to_der` and fingerprint calls do not re-serialize.
+///
+/// Equality and hashing are keyed on the DER bytes (consistent with `eq_der`),
+/// so the wrapper can be stored in a `HashSet` or used as a map key, e.g. to
+/// deduplicate VCEKs seen across many reports. Filling the cache never
+/// changes that key, so clippy's `mutable_key_type` lint is a false positive.
+pub struct DerCertificate {
+    cert: Certificate,
+    der: OnceCell<Vec<u8>>,
+}
+
+impl DerCertificate {
+    /// Wraps a certificate; its DER encoding is computed on first use.
+    pub fn new(cert: Certificate) -> Self {
+        DerCertificate { cert, der: OnceCell::new() }
+    }
+
+    /// Returns the wrapped certificate.
+    pub fn certificate(&self) -> &Certificate {
+        &self.cert
+    }
+
+    /// Returns the DER encoding, serializing the certificate on the first
+    /// call only.
+    ///
+    /// # Errors
+    /// Returns an error if the certificate cannot be encoded; nothing is
+    /// cached in that case.
+    pub fn to_der(&self) -> Result<&[u8], Box<dyn std::error::Error>> {
+        if let Some(der) = self.der.get() {
+            return Ok(der);
+        }
+        let der = self.cert.to_der()?;
+        Ok(self.der.get_or_init(|| der))
+    }
+
+    /// Returns an owned copy of the DER encoding, e.g. to hand to the BEAM
+    /// as a binary, without re-serializing the certificate.
+    ///
+    /// # Errors
+    /// Returns an error if the certificate cannot be encoded.
+    pub fn clone_der(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        Ok(self.to_der()?.to_vec())
+    }
+
+    /// Returns the fingerprint of the cached DER encoding; equal to
+    /// `fingerprint` on the wrapped certificate.
+    ///
+    /// # Errors
+    /// Returns an error if the certificate cannot be encoded or the digest
+    /// cannot be computed.
+    pub fn fingerprint(&self, digest: MessageDigest) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        Ok(openssl::hash::hash(digest, self.to_der()?)?.to_vec())
+    }
+
+    /// The DER bytes used as the comparison and hashing key. A parsed
+    /// certificate always re-encodes; should serialization ever fail for a
+    /// corrupt certificate, it is keyed as an empty encoding.
+    fn key(&self) -> &[u8] {
+        self.to_der().unwrap_or_default()
+    }
+
+    /// Unwraps the certificate.
+    pub fn into_inner(self) -> Certificate {
+        self.cert
+    }
+}
+
+impl From<Certificate> for DerCertificate {
+    fn from(cert: Certificate) -> Self {
+        DerCertificate::new(cert)
+    }
+}
+
+/// Parses PEM text, e.g. a certificate inlined in a configuration file.
+impl FromStr for DerCertificate {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        if identify_format(text.as_bytes()) != CertFormat::Pem {
+            return Err(CertFormatError::NotPem.into());
+        }
+        Ok(DerCertificate::new(from_pem(text.as_bytes())?))
+    }
+}
+
+impl PartialEq for DerCertificate {
+    fn eq(&self, other: &Self) -> bool {
+        ct_eq(self.key(), other.key())
+    }
+}
+
+impl Eq for DerCertificate {}
+
+impl Hash for DerCertificate {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+/// Orders certificates by their DER bytes, so sorted bundles are stable
+/// regardless of the order the certificates were loaded in.
+impl Ord for DerCertificate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(other.key())
+    }
+}
+
+impl PartialOrd for DerCertificate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Sorts certificates into a deterministic order (by DER bytes), e.g. before
+/// hashing a bundle.
+pub fn sort_by_der(certs: &mut Vec<Certificate>) {
+    let mut keyed: Vec<DerCertificate> = certs.drain(..).map(DerCertificate::new).collect();
+    keyed.sort();
+    certs.extend(keyed.into_iter().map(DerCertificate::into_inner));
+}
+
+/// The role a certificate plays in an AMD SEV-SNP chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CertType {
+    /// AMD Root Key: a self-signed CA.
+    Ark,
+    /// AMD SEV Key: a CA issued by the ARK.
+    Ask,
+    /// Versioned Chip Endorsement Key: the leaf that signs reports.
+    Vcek,
+}
+
+/// Reports whether the certificate is self-issued.
+///
+/// Key identifiers are preferred when both are present (the AKID of a
+/// self-signed certificate matches its own SKID); otherwise the subject and
+/// issuer names are compared.
+///
+/// # Errors
+/// Returns an error if the certificate's extensions or names cannot be decoded.
+pub fn is_self_issued(cert: &Certificate) -> Result<bool, Box<dyn std::error::Error>> {
+    if let (Some(skid), Some(akid)) = (subject_key_id(cert)?, authority_key_id(cert)?) {
+        return Ok(skid == akid);
+    }
+    let x509: &X509 = cert.into();
+    Ok(x509.subject_name().to_der()? == x509.issuer_name().to_der()?)
+}
+
+/// Classifies a certificate by its position in the chain.
+/// Self-issued CAs are ARKs, other CAs are ASKs, and non-CAs are VCEKs.
+///
+/// Classification relies only on the certificate's structure, not on its
+/// CN, so it holds for every product line; use `product_line` to tell which.
+///
+/// # Errors
+/// Returns an error if the certificate's extensions or names cannot be decoded.
+pub fn classify(cert: &Certificate) -> Result<CertType, Box<dyn std::error::Error>> {
+    if !is_ca(cert)? {
+        return Ok(CertType::Vcek);
+    }
+    if is_self_issued(cert)? {
+        Ok(CertType::Ark)
+    } else {
+        Ok(CertType::Ask)
+    }
+}
+
+/// Returns the certificate's subject common name.
+///
+/// # Errors
+/// Returns an error if the subject has no CN or it is not valid UTF-8.
+pub fn common_name(cert: &Certificate) -> Result<String, Box<dyn std::error::Error>> {
+    let x509: &X509 = cert.into();
+    let entry = x509
+        .subject_name()
+        .entries_by_nid(Nid::COMMONNAME)
+        .next()
+        .ok_or("Certificate subject has no common name")?;
+    Ok(String::from_utf8(entry.data().as_slice().to_vec())?)
+}
+
+/// Determines which product line issued the certificate from its issuer CN.
+///
+/// AMD names its CAs `ARK-<product>` and `SEV-<product>` for every product
+/// line, so the ARK and ASK are matched by their issuer ARK and the VCEK or
+/// VLEK (whose own CN carries no product) by its issuing ASK.
+///
+/// # Errors
+/// Returns an error if the issuer has no CN or it names no known product line.
+pub fn product_line(cert: &Certificate) -> Result<ProductLine, Box<dyn std::error::Error>> {
+    let x509: &X509 = cert.into();
+    let entry = x509
+        .issuer_name()
+        .entries_by_nid(Nid::COMMONNAME)
+        .next()
+        .ok_or("Certificate issuer has no common name")?;
+    let issuer = String::from_utf8(entry.data().as_slice().to_vec())?;
+    ProductLine::from_cn_suffix(&issuer)
+        .ok_or_else(|| format!("Unrecognized issuer common name: {issuer}").into())
+}
+
+/// Returns the chain's topology as `(issuer CN, subject CN)` pairs, from the
+/// self-signed ARK down to the VCEK, so it can be rendered for debugging.
+///
+/// # Errors
+/// Returns an error if a certificate's common name cannot be read.
+pub fn chain_edges(chain: &Chain) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    let (ark, ask, vek) = (
+        common_name(&chain.ca.ark)?,
+        common_name(&chain.ca.ask)?,
+        common_name(&chain.vek)?,
+    );
+    Ok(vec![(ark.clone(), ark.clone()), (ark, ask.clone()), (ask, vek)])
+}
+
+/// Splits the PEM bundle returned by KDS's `cert_chain` endpoint into the
+/// ARK and ASK, assigning each by its classified role rather than by its
+/// position in the bundle.
+///
+/// # Errors
+/// Returns an error if the bundle cannot be parsed or does not contain
+/// exactly one ARK and one ASK.
+pub fn ca_chain_from_kds_response(pem: &[u8]) -> Result<ca::Chain, Box<dyn std::error::Error>> {
+    let (mut ark, mut ask) = (None, None);
+    for x509 in X509::stack_from_pem(pem)? {
+        let cert = Certificate::from(x509);
+        let slot = match classify(&cert)? {
+            CertType::Ark => &mut ark,
+            CertType::Ask => &mut ask,
+            CertType::Vcek => return Err("KDS certificate chain unexpectedly contains a VCEK".into()),
+        };
+        if slot.replace(cert).is_some() {
+            return Err("KDS certificate chain contains more than one certificate for the same role".into());
+        }
+    }
+    Ok(ca::Chain {
+        ark: ark.ok_or("KDS certificate chain is missing the ARK")?,
+        ask: ask.ok_or("KDS certificate chain is missing the ASK")?,
+    })
+}
+
+/// Loads a PEM bundle containing the ARK, ASK and VCEK in any order and
+/// assembles a verified `Chain` from it.
+///
+/// # Arguments
+/// * `path` - Path to the `.pem` bundle (e.g., `chain.pem`).
+///
+/// # Errors
+/// Returns an error if the file cannot be read, a role is missing or
+/// duplicated, or the assembled chain does not verify.
+pub fn chain_from_pem_file<P: AsRef<Path>>(path: P) -> Result<Chain, Box<dyn std::error::Error>> {
+    let pem = std::fs::read(path)?;
+
+    // Step 1: Split the bundle and classify each certificate by role.
+    let (mut ark, mut ask, mut vek) = (None, None, None);
+    for x509 in X509::stack_from_pem(&pem)? {
+        let cert = Certificate::from(x509);
+        let slot = match classify(&cert)? {
+            CertType::Ark => &mut ark,
+            CertType::Ask => &mut ask,
+            CertType::Vcek => &mut vek,
+        };
+        if slot.replace(cert).is_some() {
+            return Err("PEM bundle contains more than one certificate for the same role".into());
+        }
+    }
+
+    // Step 2: Assemble the chain, naming any role that is missing.
+    let chain = Chain {
+        ca: ca::Chain {
+            ark: ark.ok_or("PEM bundle is missing the ARK")?,
+            ask: ask.ok_or("PEM bundle is missing the ASK")?,
+        },
+        vek: vek.ok_or("PEM bundle is missing the VCEK")?,
+    };
+
+    // Step 3: Verify the signatures and each certificate's role.
+    (&chain).verify()?;
+    check_chain_roles(&chain)?;
+    Ok(chain)
+}
+
+/// Verifies the chain link by link, recording the time spent on and the
+/// outcome of each signature check in `metrics`.
+///
+/// This performs the same checks as verifying `&Chain` directly; callers that
+/// do not need instrumentation should keep using `Verifiable::verify`.
+///
+/// # Errors
+/// Returns the error from the first link that fails to verify.
+pub fn verify_chain_with_metrics(
+    chain: &Chain,
+    metrics: &mut VerifyMetrics,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let links = [
+        (&chain.ca.ark, &chain.ca.ark),
+        (&chain.ca.ark, &chain.ca.ask),
+        (&chain.ca.ask, &chain.vek),
+    ];
+    for (index, (signer, signee)) in links.into_iter().enumerate() {
+        let start = Instant::now();
+        let result = (signer, signee).verify();
+        let link = metrics.record(start.elapsed(), result.is_ok());
+        match index {
+            0 => metrics.ark_self = Some(link),
+            1 => metrics.ark_to_ask = Some(link),
+            _ => metrics.ask_to_vek = Some(link),
+        }
+        result?;
+    }
+    Ok(())
+}
+
+/// Verifies a chain presented one certificate at a time, root first, so it
+/// can be validated as it is read from a stream instead of loaded whole.
+///
+/// Each pushed certificate must be signed by its predecessor, and the first
+/// must be self-signed. SEV-SNP chains are always an ARK, an ASK and an
+/// endorsement key, so at most `CHAIN_VERIFIER_MAX_LEN` certificates are
+/// accepted and any further push is rejected. Besides the leaf, only the
+/// root and the most recent CA are kept, so `verify` can check the names
+/// linking ARK, ASK and VCEK.
+#[derive(Default)]
+pub struct ChainVerifier {
+    root: Option<Certificate>,
+    issuer: Option<Certificate>,
+    leaf: Option<Certificate>,
+    len: usize,
+}
+
+/// Number of certificates in an SEV-SNP chain: ARK, ASK and VCEK (or VLEK).
+pub const CHAIN_VERIFIER_MAX_LEN: usize = 3;
+
+impl ChainVerifier {
+    /// Creates a verifier that expects the self-signed root first.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks the next certificate's signature against the current issuer
+    /// and, if it verifies, makes it the issuer of the next one.
+    ///
+    /// # Errors
+    /// Returns an error if `CHAIN_VERIFIER_MAX_LEN` certificates were already
+    /// pushed, the signature does not verify, or a CA is expected and the
+    /// certificate is not one. The verifier is left unchanged.
+    pub fn push(&mut self, cert: Certificate) -> Result<(), Box<dyn std::error::Error>> {
+        if self.len == CHAIN_VERIFIER_MAX_LEN {
+            return Err(format!(
+                "SEV-SNP chains hold at most {CHAIN_VERIFIER_MAX_LEN} certificates (ARK, ASK, endorsement key)"
+            )
+            .into());
+        }
+        if let Some(previous) = &self.leaf {
+            if !is_ca(previous)? {
+                return Err(format!("Certificate {} was pushed after a non-CA leaf", self.len).into());
+            }
+        }
+        let issuer_key = match &self.leaf {
+            Some(previous) => previous.public_key()?,
+            None => cert.public_key()?,
+        };
+        let x509: &X509 = (&cert).into();
+        if !x509.verify(&issuer_key)? {
+            return Err(format!("Certificate {} is not signed by its predecessor", self.len).into());
+        }
+        if self.root.is_none() {
+            self.root = Some(cert.clone());
+        }
+        self.issuer = self.leaf.replace(cert);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Returns how many certificates have been accepted so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Reports whether no certificate has been accepted yet.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Finishes verification and returns the leaf certificate.
+    ///
+    /// # Errors
+    /// Returns an error if the chain is not exactly an ARK, ASK and
+    /// endorsement key, the last one is a CA, or the names do not link up
+    /// (see `check_chain_names`).
+    pub fn verify(self) -> Result<Certificate, Box<dyn std::error::Error>> {
+        if self.len != CHAIN_VERIFIER_MAX_LEN {
+            return Err(format!(
+                "Chain must contain an ARK, ASK and endorsement key, got {} certificates",
+                self.len
+            )
+            .into());
+        }
+        let (Some(ark), Some(ask), Some(vek)) = (self.root, self.issuer, self.leaf) else {
+            return Err("Chain has no leaf certificate".into());
+        };
+        if is_ca(&vek)? {
+            return Err("Chain ends in a CA rather than an endorsement key".into());
+        }
+        let chain = Chain {
+            ca: ca::Chain { ark, ask },
+            vek,
+        };
+        check_chain_names(&chain)?;
+        Ok(chain.vek)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{
+        custom_extension, generate_key, generate_test_ca_chain, generate_test_chain, mint, mint_with, spl_extensions,
+        temp_dir,
+    };
+    use crate::clock::FixedClock;
+    use openssl::x509::extension::{BasicConstraints, KeyUsage};
+    use openssl::x509::X509Extension;
+
+    /// The Milan ASK and ARK as served by KDS.
+    const MILAN_CERT_CHAIN: &[u8] = include_bytes!("../../../certificates/amd-vcek-v1-Milan-cert_chain.pem");
+
+    /// The same chain as a DER PKCS#7 bundle, as exported by
+    /// `openssl crl2pkcs7 -nocrl -outform DER`.
+    const MILAN_CERT_CHAIN_P7B: &[u8] = include_bytes!("../../../certificates/amd-vcek-v1-Milan-cert_chain.p7b");
+
+    #[test]
+    fn classifies_milan_kds_chain() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        assert_eq!(classify(&ca.ark).unwrap(), CertType::Ark);
+        assert_eq!(classify(&ca.ask).unwrap(), CertType::Ask);
+        assert_eq!(product_line(&ca.ark).unwrap(), ProductLine::Milan);
+        assert_eq!(product_line(&ca.ask).unwrap(), ProductLine::Milan);
+    }
+
+    #[test]
+    fn classifies_genoa_ark() {
+        let ark = from_pem(sev::certs::snp::builtin::genoa::ARK).unwrap();
+        assert_eq!(classify(&ark).unwrap(), CertType::Ark);
+        assert_eq!(product_line(&ark).unwrap(), ProductLine::Genoa);
+    }
+
+    #[test]
+    fn classifies_turin_ca_chain() {
+        let ca = generate_test_ca_chain("Turin").unwrap();
+        assert_eq!(classify(&ca.ark).unwrap(), CertType::Ark);
+        assert_eq!(classify(&ca.ask).unwrap(), CertType::Ask);
+        assert_eq!(product_line(&ca.ark).unwrap(), ProductLine::Turin);
+        assert_eq!(product_line(&ca.ask).unwrap(), ProductLine::Turin);
+    }
+
+    #[test]
+    fn rejects_unknown_product_line() {
+        let ca = generate_test_ca_chain("Naples").unwrap();
+        assert!(product_line(&ca.ark).is_err());
+    }
+
+    #[test]
+    fn verifies_chain_pushed_one_certificate_at_a_time() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let mut verifier = ChainVerifier::new();
+        for cert in [chain.ca.ark.clone(), chain.ca.ask.clone(), chain.vek.clone()] {
+            verifier.push(cert).unwrap();
+        }
+        assert_eq!(verifier.len(), 3);
+        let leaf = verifier.verify().unwrap();
+        assert!(eq_der(&leaf, &chain.vek).unwrap());
+    }
+
+    #[test]
+    fn rejects_pushed_certificate_from_another_chain() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let (other, _) = generate_test_chain().unwrap();
+        let mut verifier = ChainVerifier::new();
+        verifier.push(chain.ca.ark).unwrap();
+        assert!(verifier.push(other.ca.ask).is_err());
+        assert_eq!(verifier.len(), 1);
+    }
+
+    #[test]
+    fn rejects_chain_with_mismatched_names() {
+        // The ASK is signed by the ARK's key but names a different issuer.
+        let (ark_key, ask_key, vek_key) =
+            (generate_key().unwrap(), generate_key().unwrap(), generate_key().unwrap());
+        let mut verifier = ChainVerifier::new();
+        verifier.push(mint("ARK-Test", &ark_key, "ARK-Test", &ark_key, 1, true).unwrap()).unwrap();
+        verifier.push(mint("SEV-Test", &ask_key, "ARK-Other", &ark_key, 2, true).unwrap()).unwrap();
+        verifier.push(mint("SEV-VCEK", &vek_key, "SEV-Test", &ask_key, 3, false).unwrap()).unwrap();
+        let err = verifier.verify().unwrap_err();
+        assert!(err.to_string().contains("ASK"), "{err}");
+    }
+
+    #[test]
+    fn rejects_incomplete_chain() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let mut verifier = ChainVerifier::new();
+        verifier.push(chain.ca.ark).unwrap();
+        verifier.push(chain.ca.ask).unwrap();
+        assert!(verifier.verify().is_err());
+    }
+
+    #[test]
+    fn rejects_certificates_beyond_an_snp_chain() {
+        let (ark_key, ask_key, sub_key, vek_key) =
+            (generate_key().unwrap(), generate_key().unwrap(), generate_key().unwrap(), generate_key().unwrap());
+        let mut verifier = ChainVerifier::new();
+        verifier.push(mint("ARK-Test", &ark_key, "ARK-Test", &ark_key, 1, true).unwrap()).unwrap();
+        verifier.push(mint("SEV-Test", &ask_key, "ARK-Test", &ark_key, 2, true).unwrap()).unwrap();
+        verifier.push(mint("SEV-Sub", &sub_key, "SEV-Test", &ask_key, 3, true).unwrap()).unwrap();
+        let err = verifier
+            .push(mint("SEV-VCEK", &vek_key, "SEV-Sub", &sub_key, 4, false).unwrap())
+            .unwrap_err();
+        assert!(err.to_string().contains("at most 3"), "{err}");
+        assert_eq!(verifier.len(), CHAIN_VERIFIER_MAX_LEN);
+    }
+
+    #[test]
+    fn canonical_fixture_is_canonical_der() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        for cert in [&ca.ark, &ca.ask] {
+            assert!(is_canonical_der(&cert.to_der().unwrap()).unwrap());
+        }
+    }
+
+    #[test]
+    fn ber_padded_certificate_is_not_canonical_der() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let der = ca.ark.to_der().unwrap();
+        let mut padded = vec![0x30, 0x83, 0x00];
+        padded.extend_from_slice(&der[2..]);
+        assert!(!matches!(is_canonical_der(&padded), Ok(true)));
+    }
+
+    #[test]
+    fn reports_extension_oids_in_dotted_form() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let oids: Vec<String> = extensions(&ca.ark).unwrap().into_iter().map(|ext| ext.oid).collect();
+        assert!(oids.iter().any(|oid| oid == OID_KEY_USAGE), "{oids:?}");
+        assert!(oids.iter().all(|oid| oid.split('.').all(|arc| arc.parse::<u64>().is_ok())));
+        assert!(find_extension(&ca.ark, OID_BASIC_CONSTRAINTS).unwrap().is_some());
+        assert!(amd_extension_raw(&ca.ark, "2.5.29.15").is_err());
+    }
+
+    #[test]
+    fn lists_fixture_extensions_with_criticality_and_value() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        for cert in [&ca.ark, &ca.ask] {
+            let exts = extensions(cert).unwrap();
+            let critical = |oid: &str| exts.iter().find(|ext| ext.oid == oid).map(|ext| ext.critical);
+            assert_eq!(critical(OID_KEY_USAGE), Some(true));
+            assert_eq!(critical(OID_BASIC_CONSTRAINTS), Some(true));
+            assert_eq!(critical(OID_SUBJECT_KEY_ID), Some(false));
+            assert_eq!(critical("2.5.29.31"), Some(false));
+        }
+
+        let ark_skid = hex::decode("041485ac1ad143f7c8ac55d4c51d4148abd5784ad453").unwrap();
+        let skid = find_extension(&ca.ark, OID_SUBJECT_KEY_ID).unwrap().unwrap();
+        assert_eq!(skid, CertExtension { oid: OID_SUBJECT_KEY_ID.to_string(), critical: false, value: ark_skid });
+        assert!(find_extension(&ca.ark, OID_AUTHORITY_KEY_ID).unwrap().is_none());
+        assert!(find_extension(&ca.ask, OID_AUTHORITY_KEY_ID).unwrap().is_some());
+    }
+
+    #[test]
+    fn fixture_uses_amd_rsa_pss_params() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        ensure_rsa_pss_params(&ca.ark).unwrap();
+        ensure_rsa_pss_params(&ca.ask).unwrap();
+    }
+
+    #[test]
+    fn rejects_deviant_pss_salt_length() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let mut der = ca.ask.to_der().unwrap();
+        // The outer signatureAlgorithm is the last to carry `[2] INTEGER 48`.
+        let salt = [0xa2, 0x03, 0x02, 0x01, PSS_SALT_LEN];
+        let at = der.windows(salt.len()).rposition(|w| w == salt).unwrap();
+        der[at + 4] = 32;
+        let err = ensure_rsa_pss_params(&from_der(&der).unwrap()).unwrap_err();
+        assert!(err.to_string().contains("salt"), "{err}");
+    }
+
+    #[test]
+    fn strict_verification_rejects_non_pss_chain() {
+        let (chain, _) = generate_test_chain().unwrap();
+        (&chain).verify().unwrap();
+        check_chain_roles(&chain).unwrap();
+        let err = verify_chain_strict(&chain).unwrap_err();
+        assert!(err.to_string().starts_with("ARK: "), "{err}");
+    }
+
+    #[test]
+    fn cached_der_matches_reencoding() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let cached = DerCertificate::new(ca.ask.clone());
+        let first = cached.to_der().unwrap();
+        assert_eq!(first, ca.ask.to_der().unwrap());
+        // Later calls hand back the bytes cached by the first one.
+        let second = cached.to_der().unwrap();
+        assert_eq!(second.as_ptr(), first.as_ptr());
+        assert_eq!(second, cached.certificate().to_der().unwrap());
+        assert_eq!(cached.clone_der().unwrap(), first);
+        assert_eq!(
+            cached.fingerprint(MessageDigest::sha384()).unwrap(),
+            sha384_fingerprint(&ca.ask).unwrap()
+        );
+    }
+
+    #[test]
+    fn converts_fixture_expiry_to_unix_time() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        // ARK-Milan: notBefore 2020-10-22 17:23:05 UTC, notAfter 2045-10-22 17:23:05 UTC.
+        assert_eq!(not_before_unix(&ca.ark).unwrap(), 1_603_387_385);
+        assert_eq!(not_after_unix(&ca.ark).unwrap(), 2_392_305_785);
+        assert_eq!(seconds_until_expiry(&ca.ark, 2_392_305_685).unwrap(), 100);
+        assert!(seconds_until_expiry(&ca.ark, 2_392_305_885).unwrap() < 0);
+    }
+
+    #[test]
+    fn fixture_cas_assert_ca_true() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        assert!(is_ca(&ca.ark).unwrap());
+        assert!(is_ca(&ca.ask).unwrap());
+        let (chain, _) = generate_test_chain().unwrap();
+        assert!(!is_ca(&chain.vek).unwrap());
+    }
+
+    #[test]
+    fn rejects_chain_whose_leaf_is_a_ca() {
+        let (ark_key, ask_key, vek_key) =
+            (generate_key().unwrap(), generate_key().unwrap(), generate_key().unwrap());
+        let chain = Chain {
+            ca: ca::Chain {
+                ark: mint("ARK-Test", &ark_key, "ARK-Test", &ark_key, 1, true).unwrap(),
+                ask: mint("SEV-Test", &ask_key, "ARK-Test", &ark_key, 2, true).unwrap(),
+            },
+            vek: mint("SEV-VCEK", &vek_key, "SEV-Test", &ask_key, 3, true).unwrap(),
+        };
+        (&chain).verify().unwrap();
+        let err = check_chain_roles(&chain).unwrap_err();
+        assert!(err.to_string().contains("VCEK"), "{err}");
+    }
+
+    #[test]
+    fn fixture_ark_permits_key_cert_sign() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let usage = key_usage(&ca.ark).unwrap();
+        assert!(usage.key_cert_sign);
+        assert!(usage.crl_sign);
+        assert!(!usage.digital_signature);
+        assert!(key_usage(&ca.ask).unwrap().key_cert_sign);
+    }
+
+    #[test]
+    fn rejects_ask_without_key_cert_sign() {
+        let (ark_key, ask_key) = (generate_key().unwrap(), generate_key().unwrap());
+        let extensions = vec![
+            BasicConstraints::new().critical().ca().build().unwrap(),
+            KeyUsage::new().critical().crl_sign().build().unwrap(),
+        ];
+        let ca = ca::Chain {
+            ark: mint("ARK-Test", &ark_key, "ARK-Test", &ark_key, 1, true).unwrap(),
+            ask: mint_with("SEV-Test", &ask_key, "ARK-Test", &ark_key, 2, extensions).unwrap(),
+        };
+        let err = check_ca_roles(&ca).unwrap_err();
+        assert!(err.to_string().contains("ASK KeyUsage"), "{err}");
+    }
+
+    #[test]
+    fn reads_certificate_from_cursor() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let cert = from_reader(std::io::Cursor::new(MILAN_CERT_CHAIN)).unwrap();
+        // The first block of the KDS response is the ASK.
+        assert!(eq_der(&cert, &ca.ask).unwrap());
+        assert!(from_reader(std::io::Cursor::new(Vec::new())).is_err());
+    }
+
+    #[test]
+    // The lazily filled DER cache is interior mutability, but filling it
+    // never changes the key the certificate hashes to.
+    #[allow(clippy::mutable_key_type)]
+    fn deduplicates_certificates_in_hash_set() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let mut set = std::collections::HashSet::new();
+        assert!(set.insert(DerCertificate::new(ca.ark.clone())));
+        assert!(!set.insert(DerCertificate::new(ca.ark.clone())));
+        assert_eq!(set.len(), 1);
+        set.insert(DerCertificate::new(ca.ask));
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn rejects_empty_input_with_typed_error() {
+        type Parser = fn(&[u8]) -> Result<Certificate, Box<dyn std::error::Error>>;
+        let parsers: [(&str, Parser); 5] = [
+            ("from_bytes", from_bytes),
+            ("from_pem", from_pem),
+            ("from_der", from_der),
+            ("from_hex", from_hex),
+            ("from_base64", from_base64),
+        ];
+        for (name, parse) in parsers {
+            let err = parse(b"").err().unwrap();
+            assert!(
+                matches!(err.downcast_ref::<CertFormatError>(), Some(CertFormatError::EmptyInput)),
+                "{name}: {err}"
+            );
+        }
+        let err = from_pem(b" \n\t").err().unwrap();
+        assert!(matches!(err.downcast_ref::<CertFormatError>(), Some(CertFormatError::EmptyInput)));
+    }
+
+    /// Re-wraps a PEM certificate's base64 body at `width` columns with
+    /// `newline` line endings and trailing whitespace.
+    fn rewrap_pem(pem: &[u8], width: usize, newline: &str) -> Vec<u8> {
+        let body: String = std::str::from_utf8(pem)
+            .unwrap()
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect();
+        let mut out = format!("-----BEGIN CERTIFICATE-----{newline}");
+        for chunk in body.as_bytes().chunks(width) {
+            out.push_str(std::str::from_utf8(chunk).unwrap());
+            out.push_str(newline);
+        }
+        out.push_str(&format!("-----END CERTIFICATE-----{newline}  {newline}"));
+        out.into_bytes()
+    }
+
+    #[test]
+    fn normalizes_differently_wrapped_pems() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let pem = ca.ark.to_pem().unwrap();
+        let narrow = from_pem(&rewrap_pem(&pem, 40, "\n")).unwrap();
+        let wide = from_pem(&rewrap_pem(&pem, 76, "\r\n")).unwrap();
+        let normalized = normalized_pem(&narrow).unwrap();
+        assert_eq!(normalized, normalized_pem(&wide).unwrap());
+        assert_eq!(normalized, pem);
+    }
+
+    #[test]
+    fn loads_chain_from_unordered_pem_bundle() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let dir = temp_dir("chain-bundle");
+        let path = dir.join("chain.pem");
+        let mut bundle = chain.vek.to_pem().unwrap();
+        bundle.extend(chain.ca.ark.to_pem().unwrap());
+        bundle.extend(chain.ca.ask.to_pem().unwrap());
+        std::fs::write(&path, &bundle).unwrap();
+        let loaded = chain_from_pem_file(&path).unwrap();
+        assert!(chain_eq_der(&loaded, &chain).unwrap());
+
+        let mut missing_vcek = chain.ca.ask.to_pem().unwrap();
+        missing_vcek.extend(chain.ca.ark.to_pem().unwrap());
+        std::fs::write(&path, &missing_vcek).unwrap();
+        let err = chain_from_pem_file(&path).unwrap_err();
+        assert!(err.to_string().contains("missing the VCEK"), "{err}");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// 2050-01-01T00:00:00Z, after every certificate in the fixtures expires.
+    const YEAR_2050: i64 = 2_524_608_000;
+
+    #[test]
+    fn fixture_is_expired_by_a_clock_set_to_2050() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let clock = FixedClock(YEAR_2050);
+        assert!(seconds_until_expiry_with(&ca.ark, &clock).unwrap() < 0);
+        assert!(!is_valid_at(&ca.ark, clock.now_unix()).unwrap());
+        assert!(is_valid_at(&ca.ark, 2_000_000_000).unwrap());
+
+        let (chain, _) = generate_test_chain().unwrap();
+        assert!(check_chain_validity(&chain, None).is_ok());
+        let err = check_chain_validity(&chain, Some(&clock)).unwrap_err();
+        assert!(err.to_string().contains("ARK"), "{err}");
+    }
+
+    #[test]
+    fn accepts_trusted_certificate_pem() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        // The ARK DER followed by auxiliary trust data trusting it for serverAuth,
+        // as written by `openssl x509 -addtrust serverAuth -trustout`.
+        let mut der = ca.ark.to_der().unwrap();
+        der.extend([0x30, 0x0c, 0x30, 0x0a, 0x06, 0x08, 0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x01]);
+        let mut pem = b"-----BEGIN TRUSTED CERTIFICATE-----\n".to_vec();
+        for line in openssl::base64::encode_block(&der).as_bytes().chunks(64) {
+            pem.extend_from_slice(line);
+            pem.push(b'\n');
+        }
+        pem.extend_from_slice(b"-----END TRUSTED CERTIFICATE-----\n");
+
+        assert_eq!(identify_format(&pem), CertFormat::Pem);
+        assert!(eq_der(&from_pem(&pem).unwrap(), &ca.ark).unwrap());
+        assert!(eq_der(&from_bytes(&pem).unwrap(), &ca.ark).unwrap());
+    }
+
+    #[test]
+    fn verifies_detached_signature_with_certificate_key() {
+        let key = generate_key().unwrap();
+        let cert = mint("SEV-VCEK", &key, "SEV-VCEK", &key, 1, false).unwrap();
+        let message = b"manifest endorsed by the VCEK";
+        let mut signer = openssl::sign::Signer::new(MessageDigest::sha384(), &key).unwrap();
+        signer.update(message).unwrap();
+        let signature = signer.sign_to_vec().unwrap();
+
+        assert!(verify_signature(&cert, message, &signature, MessageDigest::sha384()).unwrap());
+        assert!(!verify_signature(&cert, b"another manifest", &signature, MessageDigest::sha384()).unwrap());
+        let other = mint("SEV-VCEK", &generate_key().unwrap(), "SEV-VCEK", &key, 2, false).unwrap();
+        assert!(!verify_signature(&other, message, &signature, MessageDigest::sha384()).unwrap());
+    }
+
+    #[test]
+    fn records_metrics_for_every_link() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let mut metrics = VerifyMetrics::default();
+        verify_chain_with_metrics(&chain, &mut metrics).unwrap();
+        let links = [metrics.ark_self, metrics.ark_to_ask, metrics.ask_to_vek];
+        assert!(links.iter().all(|link| link.is_some_and(|link| link.succeeded)));
+        assert_eq!((metrics.successes, metrics.failures), (3, 0));
+        assert_eq!(metrics.total(), links.iter().flatten().map(|link| link.elapsed).sum());
+
+        let (other, _) = generate_test_chain().unwrap();
+        let mixed = Chain { ca: chain.ca, vek: other.vek };
+        let mut metrics = VerifyMetrics::default();
+        assert!(verify_chain_with_metrics(&mixed, &mut metrics).is_err());
+        assert!(!metrics.ask_to_vek.unwrap().succeeded);
+        assert_eq!((metrics.successes, metrics.failures), (2, 1));
+    }
+
+    #[test]
+    fn clears_only_benign_diagnostics_after_loading_pem() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let pem = ca.ark.to_pem().unwrap();
+        drop(ErrorStack::get());
+
+        // A "no start line" entry left by an earlier PEM scan is benign.
+        let benign = X509::from_pem(b"not a certificate").unwrap_err();
+        assert!(benign.errors().iter().all(is_benign_pem_diagnostic));
+        benign.errors()[0].put();
+        let cert = from_pem(&pem).unwrap();
+        assert_eq!(cert.to_der().unwrap(), ca.ark.to_der().unwrap());
+        assert!(ErrorStack::get().errors().is_empty());
+
+        // Anything else stays queued for the caller.
+        let genuine = X509::from_der(&[0x30, 0x00]).unwrap_err();
+        assert!(!genuine.errors().iter().all(is_benign_pem_diagnostic));
+        genuine.errors()[0].put();
+        from_pem(&pem).unwrap();
+        assert_eq!(ErrorStack::get().errors().len(), 1);
+    }
+
+    /// Mints a VCEK carrying `extensions` under a throwaway issuer.
+    fn vcek_with(extensions: Vec<X509Extension>) -> Certificate {
+        let (vek_key, ask_key) = (generate_key().unwrap(), generate_key().unwrap());
+        mint_with("SEV-VCEK", &vek_key, "SEV-Test", &ask_key, 3, extensions).unwrap()
+    }
+
+    #[test]
+    fn reads_vcek_tcb_from_spl_extensions() {
+        let tcb = TcbVersion {
+            bootloader: 3,
+            tee: 0,
+            snp: 0x81,
+            microcode: 0xd5,
+            _reserved: [0; 4],
+        };
+        let vcek = vcek_with(spl_extensions(&tcb).unwrap());
+        let issued = vcek_tcb(&vcek).unwrap();
+        assert_eq!(
+            (issued.bootloader, issued.tee, issued.snp, issued.microcode),
+            (3, 0, 0x81, 0xd5)
+        );
+    }
+
+    #[test]
+    fn rejects_non_minimal_spl_integers() {
+        for (value, reason) in [
+            (&[TAG_INTEGER, 2, 0, 5][..], "minimally encoded"),
+            (&[TAG_INTEGER, 1, 0x90][..], "minimally encoded"),
+            (&[TAG_INTEGER, 2, 1, 5][..], "does not fit in a byte"),
+        ] {
+            let mut extensions: Vec<_> = [OID_BL_SPL, OID_TEE_SPL, OID_UCODE_SPL]
+                .into_iter()
+                .map(|oid| custom_extension(oid, &[TAG_INTEGER, 1, 0]).unwrap())
+                .collect();
+            extensions.push(custom_extension(OID_SNP_SPL, value).unwrap());
+            let err = vcek_tcb(&vcek_with(extensions)).unwrap_err().to_string();
+            assert!(err.contains(OID_SNP_SPL) && err.contains(reason), "{err}");
+        }
+    }
+
+    #[test]
+    fn handles_crlf_pem() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let crlf: Vec<u8> = std::str::from_utf8(&ca.ark.to_pem().unwrap())
+            .unwrap()
+            .replace('\n', "\r\n")
+            .into_bytes();
+        assert_eq!(identify_format(&crlf), CertFormat::Pem);
+        assert_eq!(pem_label(&crlf).as_deref(), Some("CERTIFICATE"));
+        let cert = from_bytes(&crlf).unwrap();
+        let normalized = normalized_pem(&cert).unwrap();
+        assert!(!normalized.contains(&b'\r'));
+        assert_eq!(normalized, ca.ark.to_pem().unwrap());
+    }
+
+    #[test]
+    fn guesses_format_with_confidence() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        assert_eq!(CertFormat::guess(&ca.ark.to_pem().unwrap()), (CertFormat::Pem, true));
+        assert_eq!(CertFormat::guess(&ca.ark.to_der().unwrap()), (CertFormat::Der, true));
+        assert_eq!(CertFormat::guess(&[0x30, 0x82]), (CertFormat::Der, false));
+    }
+
+    #[test]
+    fn extracts_raw_hwid_extension() {
+        let hwid = [0xA5; 64];
+        let mut value = vec![TAG_OCTET_STRING, 64];
+        value.extend_from_slice(&hwid);
+        let key = generate_key().unwrap();
+        let vcek = mint_with(
+            "SEV-VCEK",
+            &key,
+            "SEV-Test",
+            &key,
+            3,
+            vec![custom_extension("1.3.6.1.4.1.3704.1.4", &value).unwrap()],
+        )
+        .unwrap();
+
+        assert_eq!(amd_extension_raw(&vcek, "1.3.6.1.4.1.3704.1.4").unwrap(), Some(value));
+        assert_eq!(amd_extension_raw(&vcek, "1.3.6.1.4.1.3704.1.3.1").unwrap(), None);
+        assert!(amd_extension_raw(&vcek, "2.5.29.14").is_err());
+    }
+
+    #[test]
+    fn verifies_fixture_ca_chain_on_its_own() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        assert!(verify_ca_chain(&ca).is_ok());
+    }
+
+    #[test]
+    fn rejects_ca_chain_with_tampered_ask() {
+        let mut ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let mut der = ca.ask.to_der().unwrap();
+        // The signature is the last element of the certificate.
+        *der.last_mut().unwrap() ^= 0x01;
+        ca.ask = Certificate::from_der(&der).unwrap();
+        assert!(verify_ca_chain(&ca).is_err());
+    }
+
+    #[test]
+    fn decodes_hex_with_embedded_whitespace() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let der = ca.ark.to_der().unwrap();
+        let pasted: String = hex::encode(&der)
+            .as_bytes()
+            .chunks(64)
+            .map(|line| format!(" {}\r\n", std::str::from_utf8(line).unwrap()))
+            .collect();
+        assert_eq!(from_hex(pasted.as_bytes()).unwrap().to_der().unwrap(), der);
+
+        let err = from_hex(b"30 82 zz").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CertFormatError>(),
+            Some(CertFormatError::InvalidEncoding { encoding: "hex", .. })
+        ));
+    }
+
+    #[test]
+    fn unpacks_pkcs7_bundle_into_member_certificates() {
+        assert_eq!(identify_format(MILAN_CERT_CHAIN_P7B), CertFormat::Pkcs7);
+        let der = |certs: Vec<Certificate>| -> Vec<Vec<u8>> { certs.iter().map(|c| c.to_der().unwrap()).collect() };
+        let certs = der(stack_from_pkcs7(MILAN_CERT_CHAIN_P7B).unwrap());
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        assert_eq!(certs.len(), 2);
+        assert!(certs.contains(&ca.ark.to_der().unwrap()));
+        assert!(certs.contains(&ca.ask.to_der().unwrap()));
+
+        let pem = Pkcs7::from_der(MILAN_CERT_CHAIN_P7B).unwrap().to_pem().unwrap();
+        assert_eq!(identify_format(&pem), CertFormat::Pkcs7);
+        assert_eq!(der(stack_from_pkcs7(&pem).unwrap()), certs);
+    }
+
+    #[test]
+    fn valid_chain_yields_its_vcek() {
+        let (chain, report) = generate_test_chain().unwrap();
+        let expected = chain.vek.to_der().unwrap();
+        let vcek = verify_into_vcek(chain).unwrap();
+        assert_eq!(vcek.to_der().unwrap(), expected);
+        assert!(verify_report_with_vcek(&vcek, &report).is_ok());
+
+        let (mut chain, _) = generate_test_chain().unwrap();
+        let (other, _) = generate_test_chain().unwrap();
+        chain.vek = other.vek;
+        assert!(verify_into_vcek(chain).is_err());
+    }
+
+    #[test]
+    fn pins_fixture_ark_fingerprints() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        assert_eq!(
+            hex::encode(sha256_fingerprint(&ca.ark).unwrap()),
+            "69d063b45344d26a2e94e1f4210de49ef555308287d4c174445c95639a540bcd"
+        );
+        assert_eq!(
+            hex::encode(sha384_fingerprint(&ca.ark).unwrap()),
+            "2f1316273dade9b896875da0acb6bc1c0547d41320ad323cbfbef6570f0305a3e7f8398d0b44bd1f36075295cefcc0db"
+        );
+    }
+
+    #[test]
+    fn strict_parsing_rejects_ambiguous_input() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        assert!(from_bytes_strict(&ca.ark.to_pem().unwrap()).is_ok());
+        assert!(from_bytes_strict(&ca.ark.to_der().unwrap()).is_ok());
+
+        let err = from_bytes_strict(&[0x30, 0x03, 0x02, 0x01]).unwrap_err();
+        assert!(matches!(err.downcast_ref::<CertFormatError>(), Some(CertFormatError::AmbiguousInput)));
+    }
+
+    #[test]
+    fn links_fixture_ask_to_ark_by_key_identifier() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let ark_skid = subject_key_id(&ca.ark).unwrap().unwrap();
+        assert_eq!(hex::encode_upper(&ark_skid), "85AC1AD143F7C8AC55D4C51D4148ABD5784AD453");
+        // AMD omits the AKID from its self-signed ARKs.
+        assert_eq!(authority_key_id(&ca.ark).unwrap(), None);
+        assert_eq!(authority_key_id(&ca.ask).unwrap(), Some(ark_skid));
+        assert_ne!(subject_key_id(&ca.ask).unwrap(), authority_key_id(&ca.ask).unwrap());
+    }
+
+    #[test]
+    fn loads_mixed_pem_and_der_directory() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let dir = temp_dir("load-dir");
+        std::fs::write(dir.join("ark.pem"), ca.ark.to_pem().unwrap()).unwrap();
+        std::fs::write(dir.join("ask.der"), ca.ask.to_der().unwrap()).unwrap();
+        let loaded = load_dir(&dir);
+
+        std::fs::write(dir.join("notes.txt"), b"not a certificate").unwrap();
+        let partial = load_dir(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].to_der().unwrap(), ca.ark.to_der().unwrap());
+        assert_eq!(loaded[1].to_der().unwrap(), ca.ask.to_der().unwrap());
+
+        let err = partial.unwrap_err();
+        let err = err.downcast_ref::<LoadDirError>().unwrap();
+        assert_eq!(err.loaded.len(), 2);
+        assert_eq!(err.failures.len(), 1);
+        assert!(err.failures[0].0.ends_with("notes.txt"));
+    }
+
+    #[test]
+    fn parses_inline_pem_string() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let text = format!("\n  {}  \n", String::from_utf8(ca.ark.to_pem().unwrap()).unwrap());
+        let cert: DerCertificate = text.parse().unwrap();
+        assert_eq!(cert.certificate().to_der().unwrap(), ca.ark.to_der().unwrap());
+
+        let err = hex::encode(ca.ark.to_der().unwrap()).parse::<DerCertificate>().err().unwrap();
+        assert!(matches!(err.downcast_ref::<CertFormatError>(), Some(CertFormatError::NotPem)));
+    }
+
+    #[test]
+    fn lists_issuer_to_subject_edges() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let edges = chain_edges(&chain).unwrap();
+        let edge = |issuer: &str, subject: &str| (issuer.to_string(), subject.to_string());
+        assert_eq!(
+            edges,
+            vec![
+                edge("ARK-Test", "ARK-Test"),
+                edge("ARK-Test", "SEV-Test"),
+                edge("SEV-Test", "SEV-VCEK"),
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_fixture_ark_as_text() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let text = to_text(&ca.ark).unwrap();
+        assert!(text.contains("ARK-Milan"), "{text}");
+        assert!(text.contains("Serial Number: 65536 (0x10000)"), "{text}");
+    }
+
+    #[test]
+    fn verifies_ask_and_vcek_under_trusted_ark() {
+        let (chain, _) = generate_test_chain().unwrap();
+        assert!(verify_with_trusted_ark(&chain.ca.ask, &chain.vek, &chain.ca.ark).is_ok());
+
+        let (other, _) = generate_test_chain().unwrap();
+        assert!(verify_with_trusted_ark(&chain.ca.ask, &chain.vek, &other.ca.ark).is_err());
+    }
+
+    #[test]
+    fn rejects_trusted_ark_path_with_mismatched_names() {
+        let (ark_key, ask_key, vek_key) = (generate_key().unwrap(), generate_key().unwrap(), generate_key().unwrap());
+        let ark = mint("ARK-Test", &ark_key, "ARK-Test", &ark_key, 1, true).unwrap();
+        let ask = mint("SEV-Test", &ask_key, "ARK-Test", &ark_key, 2, true).unwrap();
+        // Signed by the ASK's key, but naming a different issuer.
+        let vcek = mint("SEV-VCEK", &vek_key, "SEV-Other", &ask_key, 3, false).unwrap();
+
+        let err = verify_with_trusted_ark(&ask, &vcek, &ark).unwrap_err();
+        assert!(err.downcast_ref::<NameMismatch>().is_some(), "{err}");
+    }
+
+    #[test]
+    fn identifies_the_pair_with_mismatched_names() {
+        let (chain, _) = generate_test_chain().unwrap();
+        check_chain_names(&chain).unwrap();
+
+        let (ark_key, ask_key, vek_key) = (generate_key().unwrap(), generate_key().unwrap(), generate_key().unwrap());
+        let ark = mint("ARK-Test", &ark_key, "ARK-Test", &ark_key, 1, true).unwrap();
+        let ask = mint("SEV-Test", &ask_key, "ARK-Test", &ark_key, 2, true).unwrap();
+        let rogue_vcek = mint("SEV-VCEK", &vek_key, "SEV-Rogue", &ask_key, 3, false).unwrap();
+        let chain = Chain { ca: ca::Chain { ark: ark.clone(), ask: ask.clone() }, vek: rogue_vcek };
+        let err = check_chain_roles(&chain).unwrap_err();
+        let mismatch = err.downcast_ref::<NameMismatch>().unwrap();
+        assert_eq!((mismatch.issuer, mismatch.subject), ("ASK", "VCEK"));
+        assert_eq!(err.to_string(), "VCEK issuer name does not match the ASK subject name");
+
+        let rogue_ask = mint("SEV-Test", &ask_key, "ARK-Rogue", &ark_key, 2, true).unwrap();
+        let vcek = mint("SEV-VCEK", &vek_key, "SEV-Test", &ask_key, 3, false).unwrap();
+        let chain = Chain { ca: ca::Chain { ark, ask: rogue_ask }, vek: vcek };
+        let err = check_chain_names(&chain).unwrap_err();
+        let mismatch = err.downcast_ref::<NameMismatch>().unwrap();
+        assert_eq!((mismatch.issuer, mismatch.subject), ("ARK", "ASK"));
+    }
+
+    #[test]
+    fn diagnoses_each_way_a_pair_can_fail() {
+        let (ask_key, vek_key, other_key) = (generate_key().unwrap(), generate_key().unwrap(), generate_key().unwrap());
+        let ask = mint("SEV-Test", &ask_key, "ARK-Test", &ask_key, 2, true).unwrap();
+        let vcek = mint("SEV-VCEK", &vek_key, "SEV-Test", &ask_key, 3, false).unwrap();
+        let all_ok = PairDiagnosis { issuer_matches_subject: true, key_usage_ok: true, signature_valid: true };
+        assert_eq!(diagnose_pair(&ask, &vcek), all_ok);
+        assert!(all_ok.is_valid());
+
+        let misnamed = mint("SEV-VCEK", &vek_key, "SEV-Other", &ask_key, 3, false).unwrap();
+        let diagnosis = diagnose_pair(&ask, &misnamed);
+        assert_eq!(diagnosis, PairDiagnosis { issuer_matches_subject: false, ..all_ok });
+        assert!(!diagnosis.is_valid());
+
+        // A VCEK is not a CA, so it may not sign certificates even if the
+        // name and signature line up.
+        let signed_by_leaf = mint("SEV-Leaf", &other_key, "SEV-VCEK", &vek_key, 4, false).unwrap();
+        assert_eq!(diagnose_pair(&vcek, &signed_by_leaf), PairDiagnosis { key_usage_ok: false, ..all_ok });
+
+        let forged = mint("SEV-VCEK", &vek_key, "SEV-Test", &other_key, 3, false).unwrap();
+        assert_eq!(diagnose_pair(&ask, &forged), PairDiagnosis { signature_valid: false, ..all_ok });
+    }
+
+    #[test]
+    fn assigns_kds_bundle_members_by_role() {
+        // KDS lists the ASK first.
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        assert_eq!(common_name(&ca.ark).unwrap(), "ARK-Milan");
+        assert_eq!(common_name(&ca.ask).unwrap(), "SEV-Milan");
+
+        let mut reversed = ca.ark.to_pem().unwrap();
+        reversed.extend(ca.ask.to_pem().unwrap());
+        let swapped = ca_chain_from_kds_response(&reversed).unwrap();
+        assert_eq!(common_name(&swapped.ark).unwrap(), "ARK-Milan");
+        assert_eq!(common_name(&swapped.ask).unwrap(), "SEV-Milan");
+    }
+
+    #[test]
+    fn rejects_vcek_with_ed25519_key() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        assert!(checked_public_key(&ca.ark).is_ok());
+
+        let issuer_key = generate_key().unwrap();
+        let ed25519 = PKey::generate_ed25519().unwrap();
+        let vcek = mint("SEV-VCEK", &ed25519, "SEV-Test", &issuer_key, 3, false).unwrap();
+
+        let err = checked_public_key(&vcek).unwrap_err();
+        let err = err.downcast_ref::<UnsupportedKeyType>().unwrap();
+        assert_eq!(err.algorithm, "ED25519");
+        assert_eq!(err.expected, "EC P-384");
+    }
+
+    #[test]
+    fn reports_fixture_expiring_within_window() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        const DAY: u64 = 24 * 60 * 60;
+        // The fixture ARK expires on 2045-10-22; take "now" to be 2023-11-14.
+        let now = 1_700_000_000;
+        assert!(!expires_within(&ca.ark, Duration::from_secs(365 * DAY), now).unwrap());
+        assert!(expires_within(&ca.ark, Duration::from_secs(30 * 365 * DAY), now).unwrap());
+        assert!(expires_within(&ca.ark, Duration::ZERO, YEAR_2050).unwrap());
+    }
+
+    #[test]
+    fn tolerates_expiry_within_clock_skew() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let expiry = not_after_unix(&chain.vek).unwrap();
+        let skew = Duration::from_secs(2 * 60 * 60);
+
+        let just_expired = Asn1Time::from_unix(expiry + 60 * 60).unwrap();
+        assert!(verify_chain_with_time(&chain, &just_expired, skew).is_ok());
+        let long_expired = Asn1Time::from_unix(expiry + 10 * 24 * 60 * 60).unwrap();
+        let err = verify_chain_with_time(&chain, &long_expired, skew).unwrap_err();
+        assert!(err.to_string().contains("clock skew"), "{err}");
+    }
+
+    #[test]
+    fn fixture_is_x509_v3_and_v1_leaf_is_rejected() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        assert_eq!(x509_version(&ca.ark), X509_V3);
+        assert_eq!(x509_version(&ca.ask), 2);
+
+        let (mut chain, _) = generate_test_chain().unwrap();
+        let key = generate_key().unwrap();
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(0).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+        builder.sign(&key, MessageDigest::sha384()).unwrap();
+        chain.vek = Certificate::from(builder.build());
+
+        assert_eq!(x509_version(&chain.vek), 0);
+        let err = check_chain_roles(&chain).unwrap_err();
+        assert_eq!(err.to_string(), "VCEK is X.509 v1 rather than v3");
+    }
+
+    #[test]
+    fn compares_chains_by_der() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let copy = Chain {
+            ca: ca::Chain {
+                ark: Certificate::from_der(&chain.ca.ark.to_der().unwrap()).unwrap(),
+                ask: Certificate::from_der(&chain.ca.ask.to_der().unwrap()).unwrap(),
+            },
+            vek: Certificate::from_der(&chain.vek.to_der().unwrap()).unwrap(),
+        };
+        assert!(chain_eq_der(&chain, &copy).unwrap());
+
+        let (other, _) = generate_test_chain().unwrap();
+        let swapped = Chain { vek: other.vek, ..copy };
+        assert!(!chain_eq_der(&chain, &swapped).unwrap());
+    }
+
+    #[test]
+    fn reads_pem_block_labels() {
+        let ca = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        assert_eq!(pem_label(&ca.ark.to_pem().unwrap()).as_deref(), Some("CERTIFICATE"));
+        let pkcs7 = Pkcs7::from_der(MILAN_CERT_CHAIN_P7B).unwrap().to_pem().unwrap();
+        assert_eq!(pem_label(&pkcs7).as_deref(), Some("PKCS7"));
+        assert_eq!(pem_label(&ca.ark.to_der().unwrap()), None);
+        assert_eq!(pem_label(b"-----BEGIN -----"), None);
+    }
+
+    #[test]
+    fn verifies_against_optional_signer() {
+        let (chain, _) = generate_test_chain().unwrap();
+        assert!(verify_with_optional_signer(Some(&chain.ca.ask), &chain.vek).is_ok());
+        assert!(verify_with_optional_signer(Some(&chain.ca.ark), &chain.vek).is_err());
+
+        let err = verify_with_optional_signer(None, &chain.vek).unwrap_err();
+        assert!(err.downcast_ref::<MissingSigner>().is_some(), "{err}");
+    }
+
+    #[test]
+    fn sorts_certificates_deterministically() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let ders = |certs: &[Certificate]| -> Vec<Vec<u8>> { certs.iter().map(|c| c.to_der().unwrap()).collect() };
+        let mut forward = vec![chain.ca.ark.clone(), chain.ca.ask.clone(), chain.vek.clone()];
+        let mut backward = vec![chain.vek.clone(), chain.ca.ark.clone(), chain.ca.ask.clone()];
+        sort_by_der(&mut forward);
+        sort_by_der(&mut backward);
+
+        let sorted = ders(&forward);
+        assert_eq!(sorted, ders(&backward));
+        assert!(sorted.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+}
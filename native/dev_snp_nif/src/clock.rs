@@ -0,0 +1,33 @@
+use std::time::SystemTime;
+
+/// Source of the current time for certificate validity checks.
+///
+/// Injecting a clock lets callers (and tests) pin "now" instead of relying
+/// on the wall clock.
+pub trait Clock {
+    /// Returns the current time in seconds since the Unix epoch.
+    fn now_unix(&self) -> i64;
+}
+
+/// The system wall clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_unix(&self) -> i64 {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0)
+    }
+}
+
+/// A clock frozen at a fixed instant, in seconds since the Unix epoch.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub i64);
+
+impl Clock for FixedClock {
+    fn now_unix(&self) -> i64 {
+        self.0
+    }
+}
@@ -0,0 +1,148 @@
+//! Extraction of SNP evidence from CBOR / COSE_Sign1 attestation envelopes,
+//! as used by RATS/EAT-style transports.
+//!
+//! The evidence is a CBOR map with the binary report under `"snp_report"`
+//! and the DER certificates under `"certs"`. It may be sent bare or as the
+//! payload of a COSE_Sign1 (tagged or not). The envelope's own signature is
+//! not checked here: trust comes from verifying the report against the chain.
+
+use ciborium::value::Value;
+use sev::certs::snp::Certificate;
+use sev::firmware::guest::AttestationReport;
+use crate::certs::from_der;
+use crate::report::parse_report_bytes;
+
+/// CBOR tag identifying a COSE_Sign1 structure.
+const COSE_SIGN1_TAG: u64 = 18;
+
+/// Returns the evidence map, unwrapping a COSE_Sign1 envelope if present.
+fn evidence_map(value: Value) -> Result<Vec<(Value, Value)>, Box<dyn std::error::Error>> {
+    let value = match value {
+        Value::Tag(COSE_SIGN1_TAG, inner) => *inner,
+        other => other,
+    };
+    match value {
+        Value::Map(entries) => Ok(entries),
+        // COSE_Sign1: [protected, unprotected, payload, signature]
+        Value::Array(mut items) if items.len() == 4 => match items.swap_remove(2) {
+            Value::Bytes(payload) => match ciborium::de::from_reader(payload.as_slice())? {
+                Value::Map(entries) => Ok(entries),
+                _ => Err("COSE_Sign1 payload is not a CBOR map".into()),
+            },
+            _ => Err("COSE_Sign1 payload is detached or not a byte string".into()),
+        },
+        _ => Err("Attestation envelope is neither a CBOR map nor a COSE_Sign1".into()),
+    }
+}
+
+/// Finds the value stored under a text key.
+fn field<'a>(entries: &'a [(Value, Value)], key: &str) -> Option<&'a Value> {
+    entries
+        .iter()
+        .find(|(k, _)| matches!(k, Value::Text(text) if text == key))
+        .map(|(_, v)| v)
+}
+
+/// Extracts the SNP report and certificate chain from a CBOR or COSE_Sign1
+/// attestation envelope.
+///
+/// # Returns
+/// The parsed report and the certificates in envelope order.
+///
+/// # Errors
+/// Returns an error if the envelope is not valid CBOR, lacks either field,
+/// or the report or a certificate does not parse.
+pub fn parse_cose_attestation(
+    bytes: &[u8],
+) -> Result<(AttestationReport, Vec<Certificate>), Box<dyn std::error::Error>> {
+    let entries = evidence_map(ciborium::de::from_reader(bytes)?)?;
+
+    let report = match field(&entries, "snp_report") {
+        Some(Value::Bytes(raw)) => parse_report_bytes(raw)?,
+        _ => return Err("Attestation envelope has no \"snp_report\" byte string".into()),
+    };
+    let certs = match field(&entries, "certs") {
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::Bytes(der) => from_der(der),
+                _ => Err("Envelope certificate is not a byte string".into()),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => return Err("Attestation envelope has no \"certs\" array".into()),
+    };
+    Ok((report, certs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::generate_test_chain;
+
+    /// A bare evidence map carrying `report` and `certs`.
+    fn evidence(report: &[u8], certs: &[Vec<u8>]) -> Value {
+        Value::Map(vec![
+            (Value::Text("snp_report".into()), Value::Bytes(report.to_vec())),
+            (
+                Value::Text("certs".into()),
+                Value::Array(certs.iter().cloned().map(Value::Bytes).collect()),
+            ),
+        ])
+    }
+
+    fn to_cbor(value: &Value) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(value, &mut bytes).unwrap();
+        bytes
+    }
+
+    /// Wraps `payload` in a tagged COSE_Sign1 with an empty signature.
+    fn cose_sign1(payload: &Value) -> Value {
+        Value::Tag(
+            COSE_SIGN1_TAG,
+            Box::new(Value::Array(vec![
+                Value::Bytes(Vec::new()),
+                Value::Map(Vec::new()),
+                Value::Bytes(to_cbor(payload)),
+                Value::Bytes(Vec::new()),
+            ])),
+        )
+    }
+
+    #[test]
+    fn parses_bare_and_cose_sign1_envelopes() {
+        let (chain, report) = generate_test_chain().unwrap();
+        let report_bytes = bincode::serialize(&report).unwrap();
+        let ders = vec![
+            chain.ca.ark.to_der().unwrap(),
+            chain.ca.ask.to_der().unwrap(),
+            chain.vek.to_der().unwrap(),
+        ];
+        let map = evidence(&report_bytes, &ders);
+
+        for envelope in [to_cbor(&map), to_cbor(&cose_sign1(&map))] {
+            let (parsed, certs) = parse_cose_attestation(&envelope).unwrap();
+            assert_eq!(bincode::serialize(&parsed).unwrap(), report_bytes);
+            let parsed_ders: Vec<Vec<u8>> = certs.iter().map(|cert| cert.to_der().unwrap()).collect();
+            assert_eq!(parsed_ders, ders);
+        }
+    }
+
+    #[test]
+    fn rejects_envelope_without_report() {
+        let map = Value::Map(vec![(Value::Text("certs".into()), Value::Array(Vec::new()))]);
+        let err = parse_cose_attestation(&to_cbor(&map)).err().unwrap();
+        assert!(err.to_string().contains("snp_report"), "{err}");
+    }
+
+    #[test]
+    fn rejects_detached_cose_payload() {
+        let detached = Value::Array(vec![
+            Value::Bytes(Vec::new()),
+            Value::Map(Vec::new()),
+            Value::Null,
+            Value::Bytes(Vec::new()),
+        ]);
+        assert!(parse_cose_attestation(&to_cbor(&detached)).is_err());
+    }
+}
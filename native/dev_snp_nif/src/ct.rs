@@ -0,0 +1,43 @@
+/// Compares two byte slices without short-circuiting on the first mismatch.
+///
+/// The running time depends only on the input lengths, never on where the
+/// inputs differ, so comparing digests, measurements, or DER blobs with this
+/// helper does not leak how many leading bytes matched. Slices of different
+/// lengths compare unequal immediately, as lengths are not secret here.
+///
+/// # Arguments
+/// * `a` - The first byte slice.
+/// * `b` - The second byte slice.
+///
+/// # Returns
+/// `true` if both slices have the same length and contents, `false` otherwise.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a
+        .iter()
+        .zip(b.iter())
+        .fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    // Keep the optimizer from turning the fold back into an early exit.
+    std::hint::black_box(diff) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_slices_of_matching_length() {
+        assert!(ct_eq(&[0xde, 0xad, 0xbe, 0xef], &[0xde, 0xad, 0xbe, 0xef]));
+        assert!(!ct_eq(&[0xde, 0xad, 0xbe, 0xef], &[0xde, 0xad, 0xbe, 0xee]));
+        assert!(!ct_eq(&[0x00, 0xad, 0xbe, 0xef], &[0xde, 0xad, 0xbe, 0xef]));
+        assert!(ct_eq(&[], &[]));
+    }
+
+    #[test]
+    fn slices_of_different_length_are_unequal() {
+        assert!(!ct_eq(&[1, 2, 3], &[1, 2]));
+        assert!(!ct_eq(&[], &[0]));
+    }
+}
@@ -0,0 +1,211 @@
+//! Minimal DER reader used to inspect certificate fields that the `openssl`
+//! crate does not expose directly (e.g., individual X.509v3 extensions).
+
+/// DER tag for a BOOLEAN.
+pub const TAG_BOOLEAN: u8 = 0x01;
+/// DER tag for an INTEGER.
+pub const TAG_INTEGER: u8 = 0x02;
+/// DER tag for a BIT STRING.
+pub const TAG_BIT_STRING: u8 = 0x03;
+/// DER tag for an OCTET STRING.
+pub const TAG_OCTET_STRING: u8 = 0x04;
+/// DER tag for an OBJECT IDENTIFIER.
+pub const TAG_OID: u8 = 0x06;
+/// DER tag for a SEQUENCE.
+pub const TAG_SEQUENCE: u8 = 0x30;
+
+/// A single DER element: its tag byte and its contents octets.
+#[derive(Debug, Clone, Copy)]
+pub struct Tlv<'a> {
+    pub tag: u8,
+    pub value: &'a [u8],
+}
+
+/// Reads one DER element from the front of `input`.
+///
+/// # Returns
+/// The element and the bytes following it, or `None` if the input is
+/// truncated or uses an encoding this reader does not support.
+pub fn read_tlv(input: &[u8]) -> Option<(Tlv<'_>, &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+
+    // Short form lengths fit in the first byte; long form lengths give the
+    // number of subsequent length bytes in the low seven bits.
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let len = rest[..count]
+            .iter()
+            .fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        (len, &rest[count..])
+    };
+
+    if rest.len() < len {
+        return None;
+    }
+    Some((Tlv { tag, value: &rest[..len] }, &rest[len..]))
+}
+
+/// Reads every DER element contained in `input`, e.g. the members of a SEQUENCE.
+///
+/// # Returns
+/// The elements in order, or `None` if any of them is malformed.
+pub fn read_all(mut input: &[u8]) -> Option<Vec<Tlv<'_>>> {
+    let mut items = Vec::new();
+    while !input.is_empty() {
+        let (tlv, rest) = read_tlv(input)?;
+        items.push(tlv);
+        input = rest;
+    }
+    Some(items)
+}
+
+/// Bit set in the tag of constructed elements (SEQUENCE, SET, explicit tags).
+const CONSTRUCTED: u8 = 0x20;
+
+/// Reports whether `input` is a sequence of elements in strict DER: every
+/// length uses the shortest form, BOOLEANs are `0x00` or `0xFF`, and the
+/// same holds recursively inside constructed elements.
+///
+/// BER leniencies such as padded long-form lengths, which OpenSSL accepts
+/// when parsing, are rejected.
+pub fn is_strict_der(mut input: &[u8]) -> bool {
+    while !input.is_empty() {
+        let Some((tlv, rest)) = read_tlv(input) else {
+            return false;
+        };
+        // The header is what precedes the contents; long form is only
+        // allowed for lengths of 0x80 or more, without leading zero bytes.
+        let header = &input[..input.len() - rest.len() - tlv.value.len()];
+        let minimal = header.len() == 2 || (header[2] != 0 && tlv.value.len() >= 0x80);
+        if !minimal {
+            return false;
+        }
+        if tlv.tag == TAG_BOOLEAN && !matches!(tlv.value, [0x00] | [0xff]) {
+            return false;
+        }
+        if tlv.tag & CONSTRUCTED != 0 && !is_strict_der(tlv.value) {
+            return false;
+        }
+        input = rest;
+    }
+    true
+}
+
+/// Encodes a dotted OID string (e.g. `"2.5.29.19"`) as DER OBJECT IDENTIFIER contents.
+///
+/// # Returns
+/// The encoded contents octets, or `None` if the string is not a valid OID.
+pub fn encode_oid(dotted: &str) -> Option<Vec<u8>> {
+    let arcs = dotted
+        .split('.')
+        .map(|arc| arc.parse::<u64>().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    if arcs.len() < 2 || arcs[0] > 2 || (arcs[0] < 2 && arcs[1] >= 40) {
+        return None;
+    }
+
+    // The first two arcs share one subidentifier; the rest are base-128.
+    let mut out = Vec::new();
+    let first = arcs[0].checked_mul(40)?.checked_add(arcs[1])?;
+    for arc in std::iter::once(first).chain(arcs[2..].iter().copied()) {
+        let mut chunk = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            chunk.push(((rest & 0x7f) as u8) | 0x80);
+            rest >>= 7;
+        }
+        out.extend(chunk.iter().rev());
+    }
+    Some(out)
+}
+
+/// Decodes DER OBJECT IDENTIFIER contents into a dotted string (e.g. `"2.5.29.19"`).
+///
+/// # Returns
+/// The dotted OID, or `None` if the contents are empty, truncated, or an
+/// arc overflows 64 bits.
+pub fn decode_oid(contents: &[u8]) -> Option<String> {
+    let mut arcs = Vec::new();
+    let mut value: u64 = 0;
+    for (i, &byte) in contents.iter().enumerate() {
+        value = value.checked_mul(128)? | u64::from(byte & 0x7f);
+        if byte & 0x80 != 0 {
+            if i + 1 == contents.len() {
+                return None;
+            }
+            continue;
+        }
+        // The first subidentifier packs the first two arcs as 40 * X + Y.
+        if arcs.is_empty() {
+            let first = (value / 40).min(2);
+            arcs.push(first);
+            arcs.push(value - first * 40);
+        } else {
+            arcs.push(value);
+        }
+        value = 0;
+    }
+    if arcs.is_empty() {
+        return None;
+    }
+    Some(arcs.iter().map(u64::to_string).collect::<Vec<_>>().join("."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::x509::X509;
+
+    /// The Milan ASK and ARK as served by KDS.
+    const MILAN_CERT_CHAIN: &[u8] = include_bytes!("../../../certificates/amd-vcek-v1-Milan-cert_chain.pem");
+
+    /// DER of the first certificate in the Milan chain fixture.
+    fn fixture_der() -> Vec<u8> {
+        X509::stack_from_pem(MILAN_CERT_CHAIN).unwrap()[0].to_der().unwrap()
+    }
+
+    /// Re-encodes the outer two-byte long-form length with a redundant
+    /// leading zero byte, as BER allows and DER does not.
+    fn pad_outer_length(der: &[u8]) -> Vec<u8> {
+        assert_eq!(&der[..2], &[TAG_SEQUENCE, 0x82]);
+        let mut padded = vec![TAG_SEQUENCE, 0x83, 0x00];
+        padded.extend_from_slice(&der[2..]);
+        padded
+    }
+
+    #[test]
+    fn accepts_canonical_certificate() {
+        assert!(is_strict_der(&fixture_der()));
+    }
+
+    #[test]
+    fn rejects_padded_long_form_length() {
+        assert!(!is_strict_der(&pad_outer_length(&fixture_der())));
+    }
+
+    #[test]
+    fn rejects_long_form_for_short_length() {
+        assert!(is_strict_der(&[TAG_OCTET_STRING, 0x01, 0xaa]));
+        assert!(!is_strict_der(&[TAG_OCTET_STRING, 0x81, 0x01, 0xaa]));
+    }
+
+    #[test]
+    fn rejects_non_canonical_boolean() {
+        assert!(is_strict_der(&[TAG_BOOLEAN, 0x01, 0xff]));
+        assert!(!is_strict_der(&[TAG_BOOLEAN, 0x01, 0x01]));
+    }
+
+    #[test]
+    fn round_trips_oids() {
+        let rsassa_pss = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0a];
+        assert_eq!(encode_oid("1.2.840.113549.1.1.10").unwrap(), rsassa_pss);
+        assert_eq!(decode_oid(&rsassa_pss).unwrap(), "1.2.840.113549.1.1.10");
+        assert!(decode_oid(&[0x86]).is_none());
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use sev::firmware::guest::AttestationReport;
+use sev::firmware::host::TcbVersion;
+use crate::report::{current_build, reported_tcb, tcb_at_least};
+
+/// The newest firmware state accepted from a chip.
+#[derive(Debug, Clone, Copy)]
+struct LastSeen {
+    build: u32,
+    tcb: TcbVersion,
+}
+
+/// Tracks the newest report accepted from each chip and rejects reports that
+/// go backwards, so a stale report captured before a firmware update cannot be
+/// replayed afterwards.
+///
+/// A report is accepted when its firmware build and every component of its
+/// reported TCB are at least those last accepted for the same chip ID.
+#[derive(Debug, Default)]
+pub struct FreshnessTracker {
+    last_seen: HashMap<[u8; 64], LastSeen>,
+}
+
+impl FreshnessTracker {
+    /// Creates a tracker that has not seen any chip yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks the report against the last one accepted from its chip and,
+    /// if it is not older, records it as the newest.
+    ///
+    /// # Errors
+    /// Returns an error if the report's build or reported TCB is older than
+    /// the last accepted one for the same chip. The tracker is left unchanged.
+    pub fn check_and_update(&mut self, report: &AttestationReport) -> Result<(), String> {
+        let seen = LastSeen {
+            build: current_build(report),
+            tcb: reported_tcb(report),
+        };
+        if let Some(last) = self.last_seen.get(&report.chip_id) {
+            if seen.build < last.build {
+                return Err(format!(
+                    "Report firmware build {} is older than the last accepted build {}",
+                    seen.build, last.build
+                ));
+            }
+            if !tcb_at_least(&seen.tcb, &last.tcb) {
+                return Err("Report TCB is older than the last accepted TCB for this chip".to_string());
+            }
+        }
+        self.last_seen.insert(report.chip_id, seen);
+        Ok(())
+    }
+
+    /// Forgets every chip, e.g. after the trusted state has been reset.
+    pub fn clear(&mut self) {
+        self.last_seen.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::parse_report;
+    use serde_json::Value;
+
+    /// A version 2 report captured from a Milan guest (build 20, SNP SVN 22).
+    const V2_REPORT_JSON: &[u8] = include_bytes!("../../../test/snp-attestation.json");
+
+    /// Returns the fixture report with the given firmware build and SNP SVN.
+    fn report(build: u8, snp: u8) -> AttestationReport {
+        let mut json: Value = serde_json::from_slice(V2_REPORT_JSON).unwrap();
+        json["current_build"] = build.into();
+        json["reported_tcb"]["snp"] = snp.into();
+        parse_report(&serde_json::to_vec(&json).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn rejects_older_build_after_newer_one() {
+        let mut tracker = FreshnessTracker::new();
+        assert!(tracker.check_and_update(&report(21, 22)).is_ok());
+        assert!(tracker.check_and_update(&report(21, 22)).is_ok());
+        let err = tracker.check_and_update(&report(20, 22)).unwrap_err();
+        assert!(err.contains("build 20"), "{err}");
+    }
+
+    #[test]
+    fn rejects_older_tcb_after_newer_one() {
+        let mut tracker = FreshnessTracker::new();
+        assert!(tracker.check_and_update(&report(20, 23)).is_ok());
+        assert!(tracker.check_and_update(&report(21, 22)).is_err());
+        // The rejected report did not replace the last accepted one.
+        assert!(tracker.check_and_update(&report(20, 23)).is_ok());
+    }
+
+    #[test]
+    fn forgets_chips_when_cleared() {
+        let mut tracker = FreshnessTracker::new();
+        tracker.check_and_update(&report(21, 22)).unwrap();
+        tracker.clear();
+        assert!(tracker.check_and_update(&report(20, 22)).is_ok());
+    }
+}
@@ -1,18 +1,35 @@
-use sev::certs::snp::{ca, Certificate};
+use sev::certs::snp::{ca, Certificate, Chain};
 use sev::firmware::host::TcbVersion;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use crate::cache::VcekCache;
+use crate::clock::SystemClock;
+use crate::kds::{KdsStore, KDS_CERT_CHAIN, KDS_VCEK};
+use crate::certs::{ca_chain_from_kds_response, check_chain_roles, check_chain_validity};
 use crate::logging::log_message;
-use reqwest::blocking::get; 
+use crate::report::{hwid_to_kds_hex, tcb_to_kds_query, tcb_to_u64};
+use crate::shared::SharedChain;
+use crate::trust::{is_trusted_root, verify_with_trusted_roots, TrustStore};
+use reqwest::blocking::get;
 
-/// Base URL for AMD's Key Distribution Service (KDS).
-const KDS_CERT_SITE: &str = "https://kdsintf.amd.com";
-/// Endpoint for the VCEK API.
-const KDS_VCEK: &str = "/vcek/v1";
-/// Endpoint for the Certificate Chain API.
-const KDS_CERT_CHAIN: &str = "cert_chain";
+/// Builds the KDS URL of a product's CA chain (ASK + ARK).
+fn cert_chain_url(site: &str, sev_prod_name: &str) -> String {
+    format!("{site}{KDS_VCEK}/{sev_prod_name}/{KDS_CERT_CHAIN}")
+}
+
+/// Builds the KDS URL of the VCEK for a chip ID and reported TCB.
+fn vcek_url(site: &str, sev_prod_name: &str, chip_id: &[u8; 64], reported_tcb: &TcbVersion) -> String {
+    format!(
+        "{site}{KDS_VCEK}/{sev_prod_name}/{}?{}",
+        hwid_to_kds_hex(chip_id),
+        tcb_to_kds_query(reported_tcb)
+    )
+}
 
 /// Requests the AMD certificate chain (ASK + ARK) for the given SEV product name.
 ///
 /// # Arguments
+/// * `site` - Base URL of the KDS API (e.g., `KDS_CERT_SITE` or a caching proxy).
 /// * `sev_prod_name` - The SEV product name (e.g., "Milan").
 ///
 /// # Returns
@@ -24,9 +41,12 @@ const KDS_CERT_CHAIN: &str = "cert_chain";
 /// # Example
 /// ```erlang
 /// {ok, CertChain} = dev_snp_nif:request_cert_chain("Milan").
-pub fn request_cert_chain(sev_prod_name: &str) -> Result<ca::Chain, Box<dyn std::error::Error>> {
-// Blocking version of reqwest
-    let url = format!("{KDS_CERT_SITE}{KDS_VCEK}/{sev_prod_name}/{KDS_CERT_CHAIN}");
+/// ```
+pub fn request_cert_chain(
+    site: &str,
+    sev_prod_name: &str,
+) -> Result<ca::Chain, Box<dyn std::error::Error>> {
+    let url = cert_chain_url(site, sev_prod_name);
     // log_message(
     //     "INFO",
     //     file!(),
@@ -38,16 +58,9 @@ pub fn request_cert_chain(sev_prod_name: &str) -> Result<ca::Chain, Box<dyn std:
     let response = get(&url)?;
     let body = response.bytes()?;
 
-    // Parse the response as a PEM-encoded certificate chain
-    let chain = openssl::x509::X509::stack_from_pem(&body)?;
-    if chain.len() < 2 {
-        return Err("Expected at least two certificates (ARK and ASK) in the chain".into());
-    }
-
-    // Convert ARK and ASK into the `ca::Chain` structure required by the SEV crate
-    let ark = chain[1].to_pem()?;
-    let ask = chain[0].to_pem()?;
-    let ca_chain = ca::Chain::from_pem(&ark, &ask)?;
+    // Parse the response as a PEM-encoded certificate chain, assigning the
+    // ARK and ASK by role since KDS lists the ASK first
+    let ca_chain = ca_chain_from_kds_response(&body)?;
 
     //log_message(
     //    "INFO",
@@ -62,6 +75,8 @@ pub fn request_cert_chain(sev_prod_name: &str) -> Result<ca::Chain, Box<dyn std:
 /// Requests the VCEK for the given chip ID and reported TCB.
 ///
 /// # Arguments
+/// * `site` - Base URL of the KDS API (e.g., `KDS_CERT_SITE` or a caching proxy).
+/// * `sev_prod_name` - The SEV product name (e.g., "Milan" or "Genoa").
 /// * `chip_id` - The unique 64-byte chip ID.
 /// * `reported_tcb` - The TCB version of the platform.
 ///
@@ -73,23 +88,15 @@ pub fn request_cert_chain(sev_prod_name: &str) -> Result<ca::Chain, Box<dyn std:
 ///
 /// # Example
 /// ```erlang
-/// {ok, VcekCert} = dev_snp_nif:request_vcek(ChipIdBinary, ReportedTcbMap).
+/// {ok, VcekCert} = dev_snp_nif:request_vcek("Milan", ChipIdBinary, ReportedTcbMap).
 /// ```
 pub fn request_vcek(
+    site: &str,
+    sev_prod_name: &str,
     chip_id: [u8; 64],
     reported_tcb: TcbVersion,
 ) -> Result<Certificate, Box<dyn std::error::Error>> {
-    use reqwest::blocking::get; // Blocking version of reqwest
-
-    let hw_id = chip_id
-        .iter()
-        .map(|byte| format!("{:02x}", byte))
-        .collect::<String>();
-
-    let url = format!(
-        "{KDS_CERT_SITE}{KDS_VCEK}/Milan/{hw_id}?blSPL={:02}&teeSPL={:02}&snpSPL={:02}&ucodeSPL={:02}",
-        reported_tcb.bootloader, reported_tcb.tee, reported_tcb.snp, reported_tcb.microcode
-    );
+    let url = vcek_url(site, sev_prod_name, &chip_id, &reported_tcb);
 
     // log_message(
     //     "INFO",
@@ -108,3 +115,315 @@ pub fn request_vcek(
     // log_message("INFO", file!(), line!(), "Successfully fetched VCEK.");
     Ok(vcek_cert)
 }
+
+/// Key identifying a platform's chain: product name, chip ID and raw reported TCB.
+type ChainCacheKey = (String, [u8; 64], u64);
+
+/// How long a verified chain is served from the cache before KDS is asked again.
+const CHAIN_CACHE_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Cache of verified chains, keyed by platform.
+static CHAIN_CACHE: OnceLock<Mutex<VcekCache<ChainCacheKey, SharedChain>>> = OnceLock::new();
+
+/// Returns the process-wide cache of verified chains.
+fn chain_cache() -> &'static Mutex<VcekCache<ChainCacheKey, SharedChain>> {
+    CHAIN_CACHE.get_or_init(|| Mutex::new(VcekCache::new(Some(CHAIN_CACHE_MAX_AGE))))
+}
+
+/// Rebuilds a cached chain and re-checks what can have changed since it was
+/// verified: that its ARK is one of this caller's `trusted_roots`, and that
+/// no certificate has expired. Its signatures are not verified again.
+fn check_cached_chain(
+    entry: &SharedChain,
+    trusted_roots: &dyn TrustStore,
+) -> Result<Chain, Box<dyn std::error::Error>> {
+    let chain = entry.to_chain()?;
+    if !is_trusted_root(&chain.ca.ark, trusted_roots)? {
+        return Err("Chain ARK does not match any trusted root".into());
+    }
+    check_chain_validity(&chain, None)?;
+    Ok(chain)
+}
+
+/// Returns the cached chain for `key`, if one is fresh.
+fn cached_chain(
+    key: &ChainCacheKey,
+    trusted_roots: &dyn TrustStore,
+) -> Result<Option<Chain>, Box<dyn std::error::Error>> {
+    let cached = chain_cache()
+        .lock()
+        .map_err(|_| "Chain cache lock poisoned")?
+        .get(key, &SystemClock)
+        .map(|entry| entry.value.clone());
+    let Some(entry) = cached else {
+        return Ok(None);
+    };
+    let chain = check_cached_chain(&entry, trusted_roots)?;
+    #[cfg(feature = "tracing")]
+    tracing::debug!("served verified chain from cache");
+    Ok(Some(chain))
+}
+
+/// Verifies a freshly fetched chain against pinned roots, then each
+/// certificate's role and validity period, returning it in cacheable form.
+fn verify_fetched_chain(
+    chain: &Chain,
+    trusted_roots: &dyn TrustStore,
+) -> Result<SharedChain, Box<dyn std::error::Error>> {
+    verify_with_trusted_roots(chain, trusted_roots)?;
+    check_chain_roles(chain)?;
+    check_chain_validity(chain, None)?;
+    #[cfg(feature = "tracing")]
+    for (role, cert) in [("ARK", &chain.ca.ark), ("ASK", &chain.ca.ask), ("VCEK", &chain.vek)] {
+        let x509: &openssl::x509::X509 = cert.into();
+        let subject = crate::certs::common_name(cert).ok();
+        let serial = x509.serial_number().to_bn().and_then(|bn| bn.to_hex_str().map(|hex| hex.to_string()));
+        tracing::debug!(
+            role,
+            subject = subject.as_deref(),
+            serial = serial.as_deref().ok(),
+            "verified chain certificate"
+        );
+    }
+    SharedChain::from_chain(chain)
+}
+
+/// Fetches the AMD certificate chain and the VCEK for a report's chip ID and
+/// reported TCB from a KDS store, then verifies the assembled chain against
+/// pinned roots.
+///
+/// Only chains whose ARK is one of `trusted_roots` are accepted, so a store
+/// cannot substitute its own self-signed root. Verified chains are cached per
+/// (product, chip ID, TCB) for up to `CHAIN_CACHE_MAX_AGE`, so repeated calls
+/// for the same platform neither contact the store nor re-verify signatures;
+/// a cached chain is only served if its ARK is one of `trusted_roots` and its
+/// certificates are still valid.
+///
+/// This call blocks: NIFs are synchronous and the crate runs no executor of
+/// its own. Callers already running on an async runtime should use
+/// `fetch_and_verify_chain_async` instead, which shares the same cache.
+///
+/// # Arguments
+/// * `store` - Where to fetch certificates from (e.g., `HttpKdsStore`).
+/// * `trusted_roots` - The AMD roots the chain's ARK must match.
+/// * `sev_prod_name` - The SEV product name (e.g., "Milan").
+/// * `chip_id` - The unique 64-byte chip ID from the attestation report.
+/// * `reported_tcb` - The reported TCB version from the attestation report.
+///
+/// # Returns
+/// A verified `Chain` containing the ARK, ASK and VCEK.
+///
+/// # Errors
+/// Returns an error if either download fails, the certificates cannot be
+/// parsed, the ARK is not trusted, or the chain does not verify.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(store, trusted_roots, chip_id), err))]
+pub fn fetch_and_verify_chain(
+    store: &dyn KdsStore,
+    trusted_roots: &dyn TrustStore,
+    sev_prod_name: &str,
+    chip_id: [u8; 64],
+    reported_tcb: TcbVersion,
+) -> Result<Chain, Box<dyn std::error::Error>> {
+    let key = (sev_prod_name.to_owned(), chip_id, tcb_to_u64(&reported_tcb));
+
+    // Step 1: Serve a previously verified chain from the cache, or fetch the
+    // CA chain and the VCEK from the store and verify them. The cache stays
+    // locked during the fetch, so concurrent callers wait for it instead of
+    // fetching the same chain again.
+    let entry = chain_cache()
+        .lock()
+        .map_err(|_| "Chain cache lock poisoned")?
+        .get_or_refresh(key, &SystemClock, || {
+            let ca = store.get_cert_chain(sev_prod_name)?;
+            let vek = store.get_vcek(sev_prod_name, chip_id, reported_tcb)?;
+            verify_fetched_chain(&Chain { ca, vek }, trusted_roots)
+        })?;
+
+    // Step 2: Check the chain against this caller's roots and the current time.
+    check_cached_chain(&entry, trusted_roots)
+}
+
+/// Asynchronous counterpart of `fetch_and_verify_chain`, fetching from the
+/// KDS API at `site` with the caller's `reqwest` client.
+///
+/// Both variants share one cache, so a chain verified by either is served
+/// to the other.
+///
+/// # Arguments
+/// * `client` - The HTTP client to issue the KDS requests with.
+/// * `site` - Base URL of the KDS API (e.g., `KDS_CERT_SITE` or a caching proxy).
+/// * `trusted_roots` - The AMD roots the chain's ARK must match.
+/// * `sev_prod_name` - The SEV product name (e.g., "Milan").
+/// * `chip_id` - The unique 64-byte chip ID from the attestation report.
+/// * `reported_tcb` - The reported TCB version from the attestation report.
+///
+/// # Returns
+/// A verified `Chain` containing the ARK, ASK and VCEK.
+///
+/// # Errors
+/// Returns an error if either download fails, the certificates cannot be
+/// parsed, the ARK is not trusted, or the chain does not verify.
+pub async fn fetch_and_verify_chain_async(
+    client: &reqwest::Client,
+    site: &str,
+    trusted_roots: &(dyn TrustStore + Sync),
+    sev_prod_name: &str,
+    chip_id: [u8; 64],
+    reported_tcb: TcbVersion,
+) -> Result<Chain, Box<dyn std::error::Error>> {
+    let key = (sev_prod_name.to_owned(), chip_id, tcb_to_u64(&reported_tcb));
+
+    // Step 1: Serve a previously verified chain from the cache.
+    if let Some(chain) = cached_chain(&key, trusted_roots)? {
+        return Ok(chain);
+    }
+
+    // Step 2: Fetch the CA chain and the VCEK from KDS.
+    let body = client
+        .get(cert_chain_url(site, sev_prod_name))
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let ca = ca_chain_from_kds_response(&body)?;
+    let der = client
+        .get(vcek_url(site, sev_prod_name, &chip_id, &reported_tcb))
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let vek = Certificate::from_der(&der)?;
+
+    // Step 3: Verify the chain and cache it for subsequent reports from this platform.
+    let chain = Chain { ca, vek };
+    let entry = verify_fetched_chain(&chain, trusted_roots)?;
+    chain_cache()
+        .lock()
+        .map_err(|_| "Chain cache lock poisoned")?
+        .insert(key, entry, &SystemClock);
+    Ok(chain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kds::HttpKdsStore;
+    use crate::test_util::generate_test_chain;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Serves the test chain over plain HTTP the way KDS lays it out, and
+    /// returns the base URL of the mock server and its request counter.
+    fn serve_kds(chain: &Chain) -> (String, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let served = requests.clone();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let site = format!("http://{}", listener.local_addr().unwrap());
+        let mut cert_chain = chain.ca.ask.to_pem().unwrap();
+        cert_chain.extend(chain.ca.ark.to_pem().unwrap());
+        let vcek = chain.vek.to_der().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let mut request_line = String::new();
+                if BufReader::new(&stream).read_line(&mut request_line).is_err() {
+                    continue;
+                }
+                served.fetch_add(1, Ordering::SeqCst);
+                let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+                let body = if path.ends_with(KDS_CERT_CHAIN) { &cert_chain } else { &vcek };
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = stream.write_all(header.as_bytes());
+                let _ = stream.write_all(body);
+            }
+        });
+        (site, requests)
+    }
+
+    /// Returns a chip ID no other test uses, so the global cache is cold.
+    fn unique_chip_id() -> [u8; 64] {
+        let mut chip_id = [0u8; 64];
+        openssl::rand::rand_bytes(&mut chip_id).unwrap();
+        chip_id
+    }
+
+    #[test]
+    fn fetches_and_verifies_chain_from_mock_kds() {
+        let (chain, report) = generate_test_chain().unwrap();
+        let (site, _) = serve_kds(&chain);
+        let store = HttpKdsStore::new(site);
+        let trusted_roots = vec![chain.ca.ark.clone()];
+
+        let fetched =
+            fetch_and_verify_chain(&store, &trusted_roots, "Test", unique_chip_id(), report.reported_tcb)
+                .unwrap();
+        assert_eq!(fetched.vek.to_der().unwrap(), chain.vek.to_der().unwrap());
+    }
+
+    #[test]
+    fn rejects_chain_from_mock_kds_with_untrusted_root() {
+        let (chain, report) = generate_test_chain().unwrap();
+        let (other, _) = generate_test_chain().unwrap();
+        let (site, _) = serve_kds(&chain);
+        let store = HttpKdsStore::new(site);
+        let trusted_roots = vec![other.ca.ark];
+
+        let result =
+            fetch_and_verify_chain(&store, &trusted_roots, "Test", unique_chip_id(), report.reported_tcb);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn serves_cached_chain_without_refetching() {
+        let (chain, report) = generate_test_chain().unwrap();
+        let (site, requests) = serve_kds(&chain);
+        let store = HttpKdsStore::new(site);
+        let trusted_roots = vec![chain.ca.ark.clone()];
+        let chip_id = unique_chip_id();
+
+        for _ in 0..2 {
+            fetch_and_verify_chain(&store, &trusted_roots, "Test", chip_id, report.reported_tcb).unwrap();
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // A caller trusting other roots is not served the cached chain.
+        let (other, _) = generate_test_chain().unwrap();
+        let other_roots = vec![other.ca.ark];
+        assert!(fetch_and_verify_chain(&store, &other_roots, "Test", chip_id, report.reported_tcb).is_err());
+    }
+
+    #[test]
+    fn fetches_and_verifies_chain_from_mock_kds_async() {
+        let (chain, report) = generate_test_chain().unwrap();
+        let (site, requests) = serve_kds(&chain);
+        let trusted_roots = vec![chain.ca.ark.clone()];
+        let chip_id = unique_chip_id();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let client = reqwest::Client::new();
+
+        let fetched = runtime
+            .block_on(fetch_and_verify_chain_async(
+                &client,
+                &site,
+                &trusted_roots,
+                "Test",
+                chip_id,
+                report.reported_tcb,
+            ))
+            .unwrap();
+        assert_eq!(fetched.vek.to_der().unwrap(), chain.vek.to_der().unwrap());
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // The blocking variant shares the cache.
+        let store = HttpKdsStore::new(site);
+        fetch_and_verify_chain(&store, &trusted_roots, "Test", chip_id, report.reported_tcb).unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+}
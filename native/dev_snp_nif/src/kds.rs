@@ -0,0 +1,171 @@
+//! Sources of KDS certificates, decoupling chain verification from whether
+//! the certificates come from AMD's live service or a local mirror.
+
+use sev::certs::snp::{ca, Certificate};
+use sev::firmware::host::TcbVersion;
+use std::path::PathBuf;
+use crate::certs::ca_chain_from_kds_response;
+#[cfg(feature = "kds-fetch")]
+use crate::helpers::{request_cert_chain, request_vcek};
+use crate::report::{hwid_to_kds_hex, tcb_to_u64};
+
+/// Base URL for AMD's Key Distribution Service (KDS).
+#[cfg(feature = "kds-fetch")]
+pub(crate) const KDS_CERT_SITE: &str = "https://kdsintf.amd.com";
+/// Endpoint for the VCEK API.
+pub(crate) const KDS_VCEK: &str = "/vcek/v1";
+/// Endpoint for the Certificate Chain API.
+pub(crate) const KDS_CERT_CHAIN: &str = "cert_chain";
+
+/// A source of the certificates served by AMD's Key Distribution Service.
+pub trait KdsStore {
+    /// Returns the CA chain (ASK + ARK) for the given SEV product name.
+    ///
+    /// # Errors
+    /// Returns an error if the chain cannot be retrieved or parsed.
+    fn get_cert_chain(&self, sev_prod_name: &str) -> Result<ca::Chain, Box<dyn std::error::Error>>;
+
+    /// Returns the VCEK for the given chip ID and reported TCB.
+    ///
+    /// # Errors
+    /// Returns an error if the VCEK cannot be retrieved or parsed.
+    fn get_vcek(
+        &self,
+        sev_prod_name: &str,
+        chip_id: [u8; 64],
+        reported_tcb: TcbVersion,
+    ) -> Result<Certificate, Box<dyn std::error::Error>>;
+}
+
+/// AMD's KDS, or a server exposing the same API, queried over HTTP(S).
+#[cfg(feature = "kds-fetch")]
+#[derive(Debug, Clone)]
+pub struct HttpKdsStore {
+    site: String,
+}
+
+#[cfg(feature = "kds-fetch")]
+impl HttpKdsStore {
+    /// Creates a store querying the KDS API at `site` (scheme and host,
+    /// without a trailing slash), e.g. a caching proxy in front of KDS.
+    pub fn new<S: Into<String>>(site: S) -> Self {
+        HttpKdsStore { site: site.into() }
+    }
+}
+
+/// Queries AMD's live KDS.
+#[cfg(feature = "kds-fetch")]
+impl Default for HttpKdsStore {
+    fn default() -> Self {
+        HttpKdsStore::new(KDS_CERT_SITE)
+    }
+}
+
+#[cfg(feature = "kds-fetch")]
+impl KdsStore for HttpKdsStore {
+    fn get_cert_chain(&self, sev_prod_name: &str) -> Result<ca::Chain, Box<dyn std::error::Error>> {
+        request_cert_chain(&self.site, sev_prod_name)
+    }
+
+    fn get_vcek(
+        &self,
+        sev_prod_name: &str,
+        chip_id: [u8; 64],
+        reported_tcb: TcbVersion,
+    ) -> Result<Certificate, Box<dyn std::error::Error>> {
+        request_vcek(&self.site, sev_prod_name, chip_id, reported_tcb)
+    }
+}
+
+/// A KDS mirror on the local filesystem, e.g. for air-gapped deployments.
+///
+/// Files are laid out like the KDS URL paths under `base`:
+/// - `vcek/v1/{product}/cert_chain` holds the PEM CA chain;
+/// - `vcek/v1/{product}/{hwid}-{tcb}` holds the DER VCEK, where `hwid` is
+///   the lowercase hex chip ID and `tcb` the reported TCB as 16 lowercase
+///   hex digits of its raw 64-bit value (see `vcek_path`).
+///
+/// Unlike the KDS URL, the VCEK file name carries no `?` query string, which
+/// is not a valid file name character on every platform.
+#[derive(Debug, Clone)]
+pub struct FsKdsStore {
+    base: PathBuf,
+}
+
+impl FsKdsStore {
+    /// Creates a store reading from the mirror rooted at `base`.
+    pub fn new<P: Into<PathBuf>>(base: P) -> Self {
+        FsKdsStore { base: base.into() }
+    }
+
+    /// Returns the mirror directory holding a product's certificates.
+    fn product_dir(&self, sev_prod_name: &str) -> PathBuf {
+        self.base
+            .join(KDS_VCEK.trim_start_matches('/'))
+            .join(sev_prod_name)
+    }
+
+    /// Returns the path the mirror stores a VCEK at, e.g. for tools
+    /// populating the mirror.
+    pub fn vcek_path(&self, sev_prod_name: &str, chip_id: &[u8; 64], reported_tcb: &TcbVersion) -> PathBuf {
+        let file_name = format!("{}-{:016x}", hwid_to_kds_hex(chip_id), tcb_to_u64(reported_tcb));
+        self.product_dir(sev_prod_name).join(file_name)
+    }
+}
+
+impl KdsStore for FsKdsStore {
+    fn get_cert_chain(&self, sev_prod_name: &str) -> Result<ca::Chain, Box<dyn std::error::Error>> {
+        let path = self.product_dir(sev_prod_name).join(KDS_CERT_CHAIN);
+        let body = std::fs::read(&path).map_err(|err| format!("{}: {err}", path.display()))?;
+        ca_chain_from_kds_response(&body)
+    }
+
+    fn get_vcek(
+        &self,
+        sev_prod_name: &str,
+        chip_id: [u8; 64],
+        reported_tcb: TcbVersion,
+    ) -> Result<Certificate, Box<dyn std::error::Error>> {
+        let path = self.vcek_path(sev_prod_name, &chip_id, &reported_tcb);
+        let der = std::fs::read(&path).map_err(|err| format!("{}: {err}", path.display()))?;
+        Ok(Certificate::from_der(&der)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certs::eq_der;
+    use crate::report::tcb_from_u64;
+    use crate::test_util::{generate_test_chain, temp_dir};
+
+    /// The Milan ASK and ARK as served by KDS.
+    const MILAN_CERT_CHAIN: &[u8] = include_bytes!("../../../certificates/amd-vcek-v1-Milan-cert_chain.pem");
+
+    #[test]
+    fn reads_certificates_from_mirror_directory() {
+        let base = temp_dir("kds-mirror");
+        let store = FsKdsStore::new(&base);
+        let product_dir = base.join("vcek/v1/Milan");
+        std::fs::create_dir_all(&product_dir).unwrap();
+        std::fs::write(product_dir.join("cert_chain"), MILAN_CERT_CHAIN).unwrap();
+
+        let (chain, _) = generate_test_chain().unwrap();
+        let chip_id: [u8; 64] = std::array::from_fn(|i| i as u8);
+        let tcb = tcb_from_u64(0x7308_0000_0000_0003);
+        let path = store.vcek_path("Milan", &chip_id, &tcb);
+        assert_eq!(path, product_dir.join(format!("{}-7308000000000003", hex::encode(chip_id))));
+        std::fs::write(&path, chain.vek.to_der().unwrap()).unwrap();
+
+        let ca = store.get_cert_chain("Milan").unwrap();
+        let expected = ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        assert!(eq_der(&ca.ark, &expected.ark).unwrap() && eq_der(&ca.ask, &expected.ask).unwrap());
+        let vcek = store.get_vcek("Milan", chip_id, tcb).unwrap();
+        assert!(eq_der(&vcek, &chain.vek).unwrap());
+
+        let err = store.get_vcek("Milan", chip_id, tcb_from_u64(0)).unwrap_err();
+        assert!(err.to_string().contains("-0000000000000000"), "{err}");
+        assert!(store.get_cert_chain("Genoa").is_err());
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
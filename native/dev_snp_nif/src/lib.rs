@@ -6,7 +6,32 @@ mod snp_support;
 mod attestation;
 mod digest;
 mod verification;
-mod helpers;
+#[cfg(feature = "kds-fetch")]
+pub mod helpers;
+mod der;
+pub mod audit;
+pub mod cache;
+pub mod cert_table;
+pub mod clock;
+#[cfg(feature = "cose")]
+pub mod cose;
+pub mod ct;
+pub mod freshness;
+pub mod kds;
+pub mod measurement;
+pub mod metrics;
+pub mod platform;
+pub mod certs;
+pub mod report;
+#[cfg(feature = "bundled-roots")]
+pub mod roots;
+pub mod secret;
+pub mod shared;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+pub mod trust;
+pub mod prelude;
+pub mod product;
 
 rustler::init!(
     "dev_snp_nif"// Module name as used in Erlang.
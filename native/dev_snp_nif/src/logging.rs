@@ -10,7 +10,7 @@ use std::time::SystemTime;
 /// - `message`: The log message.
 ///
 /// # Example
-/// ```rust
+/// ```ignore
 /// log_message("INFO", file!(), line!(), "This is a log message.");
 /// ```
 pub fn log_message(log_level: &str, file: &str, line: u32, message: &str) {
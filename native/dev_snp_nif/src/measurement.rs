@@ -0,0 +1,215 @@
+use openssl::sha::sha384;
+use std::collections::HashMap;
+use sev::firmware::guest::AttestationReport;
+
+/// Size in bytes of a guest page.
+pub const PAGE_SIZE: usize = 4096;
+/// Size in bytes of the PAGE_INFO structure hashed for each launch page.
+const PAGE_INFO_LEN: usize = 0x70;
+
+/// SNP launch page types, as passed to `SNP_LAUNCH_UPDATE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PageType {
+    Normal = 0x1,
+    Vmsa = 0x2,
+    Zero = 0x3,
+    Unmeasured = 0x4,
+    Secrets = 0x5,
+    Cpuid = 0x6,
+}
+
+/// A single page added to the guest during launch.
+#[derive(Debug, Clone)]
+pub struct LaunchPage {
+    /// Guest physical address of the page.
+    pub gpa: u64,
+    /// How the page was added.
+    pub page_type: PageType,
+    /// Page contents; only measured for `Normal` and `Vmsa` pages.
+    pub contents: Vec<u8>,
+}
+
+/// Launch-wide parameters that affect every page digest.
+#[derive(Debug, Clone)]
+pub struct LaunchParams {
+    /// Digest to start from; all zeroes for a fresh launch.
+    pub initial_digest: [u8; 48],
+    /// Whether pages are added by an Initial Migration Image.
+    pub imi: bool,
+    /// VMPL3, VMPL2 and VMPL1 permission masks granted to each page.
+    pub vmpl_perms: [u8; 3],
+}
+
+impl Default for LaunchParams {
+    fn default() -> Self {
+        LaunchParams {
+            initial_digest: [0; 48],
+            imi: false,
+            vmpl_perms: [0; 3],
+        }
+    }
+}
+
+/// Computes the expected launch measurement from the pages added to the guest.
+///
+/// Each page extends the running digest as `SHA-384(PAGE_INFO)`, where
+/// PAGE_INFO holds the current digest, the SHA-384 of the page contents
+/// (zero for pages whose contents are not measured), and the page metadata.
+///
+/// # Arguments
+/// * `pages` - The launch pages, in the order they were added.
+/// * `params` - Launch-wide parameters.
+///
+/// # Errors
+/// Returns an error if a measured page is not exactly `PAGE_SIZE` bytes.
+pub fn compute_launch_measurement(
+    pages: &[LaunchPage],
+    params: &LaunchParams,
+) -> Result<[u8; 48], String> {
+    let mut digest = params.initial_digest;
+    for page in pages {
+        // Step 1: Hash the page contents for page types that are measured.
+        let contents = match page.page_type {
+            PageType::Normal | PageType::Vmsa => {
+                if page.contents.len() != PAGE_SIZE {
+                    return Err(format!(
+                        "Page at GPA {:#x} must be {} bytes, got {}",
+                        page.gpa,
+                        PAGE_SIZE,
+                        page.contents.len()
+                    ));
+                }
+                sha384(&page.contents)
+            }
+            _ => [0; 48],
+        };
+
+        // Step 2: Build the PAGE_INFO structure and extend the digest with it.
+        let mut page_info = [0u8; PAGE_INFO_LEN];
+        page_info[0x00..0x30].copy_from_slice(&digest);
+        page_info[0x30..0x60].copy_from_slice(&contents);
+        page_info[0x60..0x62].copy_from_slice(&(PAGE_INFO_LEN as u16).to_le_bytes());
+        page_info[0x62] = page.page_type as u8;
+        page_info[0x63] = params.imi as u8;
+        page_info[0x64..0x67].copy_from_slice(&params.vmpl_perms);
+        page_info[0x68..0x70].copy_from_slice(&page.gpa.to_le_bytes());
+        digest = sha384(&page_info);
+    }
+    Ok(digest)
+}
+
+/// What a known-good launch measurement was built from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MeasurementInfo {
+    /// Version of the OVMF firmware the guest booted.
+    pub firmware_version: String,
+    /// Kernel build (e.g., the commit it was built from).
+    pub kernel: String,
+}
+
+/// Known-good launch measurements, each mapped to the firmware and kernel it
+/// was derived from.
+#[derive(Debug, Clone, Default)]
+pub struct MeasurementRegistry {
+    entries: HashMap<[u8; 48], MeasurementInfo>,
+}
+
+impl MeasurementRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a known-good measurement, replacing any previous metadata.
+    pub fn register(&mut self, measurement: [u8; 48], info: MeasurementInfo) {
+        self.entries.insert(measurement, info);
+    }
+
+    /// Returns the metadata registered for the measurement, if any.
+    pub fn lookup(&self, measurement: &[u8; 48]) -> Option<&MeasurementInfo> {
+        self.entries.get(measurement)
+    }
+
+    /// Checks that the report's measurement is a registered one.
+    ///
+    /// # Returns
+    /// The metadata of the matching measurement.
+    ///
+    /// # Errors
+    /// Returns an error carrying the hex measurement if it is not registered.
+    pub fn check(&self, report: &AttestationReport) -> Result<&MeasurementInfo, String> {
+        self.lookup(&report.measurement).ok_or_else(|| {
+            format!(
+                "Report measurement {} is not a registered known-good measurement",
+                hex::encode(report.measurement)
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::parse_report;
+
+    /// A version 2 report captured from a Milan guest.
+    const V2_REPORT_JSON: &[u8] = include_bytes!("../../../test/snp-attestation.json");
+
+    /// A normal page with patterned contents followed by a zero page.
+    fn launch_pages() -> Vec<LaunchPage> {
+        vec![
+            LaunchPage {
+                gpa: 0xffff_f000,
+                page_type: PageType::Normal,
+                contents: (0..PAGE_SIZE).map(|i| i as u8).collect(),
+            },
+            LaunchPage {
+                gpa: 0x80_0000,
+                page_type: PageType::Zero,
+                contents: Vec::new(),
+            },
+        ]
+    }
+
+    #[test]
+    fn matches_pinned_launch_digest() {
+        let digest = compute_launch_measurement(&launch_pages(), &LaunchParams::default()).unwrap();
+        assert_eq!(
+            hex::encode(digest),
+            "03ea82864e5aabf492f7e714db72aa7ac044e036de818a2067873e00972e6db0\
+             38604ddf109687df6c1b7433021c8a9f"
+        );
+    }
+
+    #[test]
+    fn ignores_contents_of_unmeasured_pages() {
+        let mut pages = launch_pages();
+        pages[1].contents = vec![0xff; PAGE_SIZE];
+        let digest = compute_launch_measurement(&pages, &LaunchParams::default()).unwrap();
+        let expected = compute_launch_measurement(&launch_pages(), &LaunchParams::default()).unwrap();
+        assert_eq!(digest, expected);
+    }
+
+    #[test]
+    fn rejects_measured_page_of_wrong_size() {
+        let mut pages = launch_pages();
+        pages[0].contents.pop();
+        assert!(compute_launch_measurement(&pages, &LaunchParams::default()).is_err());
+    }
+
+    #[test]
+    fn validates_report_with_registered_measurement() {
+        let report = parse_report(V2_REPORT_JSON).unwrap();
+        let info = MeasurementInfo {
+            firmware_version: "OVMF 1.55".to_string(),
+            kernel: "6.9.0-snp".to_string(),
+        };
+        let mut registry = MeasurementRegistry::new();
+        assert!(registry.check(&report).unwrap_err().contains(&hex::encode(report.measurement)));
+
+        registry.register(report.measurement, info.clone());
+        assert_eq!(registry.check(&report), Ok(&info));
+        assert_eq!(registry.lookup(&[0; 48]), None);
+    }
+}
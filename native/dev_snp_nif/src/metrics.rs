@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+/// Timing and outcome of verifying a single link of the certificate chain.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinkMetrics {
+    /// Time spent verifying the link's signature.
+    pub elapsed: Duration,
+    /// Whether the signature verified.
+    pub succeeded: bool,
+}
+
+/// Per-link measurements collected while verifying a chain.
+///
+/// Links are `None` until verification reaches them, so a failure part way
+/// through leaves the later links unpopulated.
+#[derive(Debug, Clone, Default)]
+pub struct VerifyMetrics {
+    /// ARK self-signature (RSA-PSS).
+    pub ark_self: Option<LinkMetrics>,
+    /// ARK signing the ASK (RSA-PSS).
+    pub ark_to_ask: Option<LinkMetrics>,
+    /// ASK signing the VCEK (RSA-PSS).
+    pub ask_to_vek: Option<LinkMetrics>,
+    /// Number of links that verified.
+    pub successes: u32,
+    /// Number of links that failed to verify.
+    pub failures: u32,
+}
+
+impl VerifyMetrics {
+    /// Records the outcome of one link, updating the success/failure counters.
+    pub(crate) fn record(&mut self, elapsed: Duration, succeeded: bool) -> LinkMetrics {
+        if succeeded {
+            self.successes += 1;
+        } else {
+            self.failures += 1;
+        }
+        LinkMetrics { elapsed, succeeded }
+    }
+
+    /// Returns the total time spent across all verified links.
+    pub fn total(&self) -> Duration {
+        [self.ark_self, self.ark_to_ask, self.ask_to_vek]
+            .iter()
+            .flatten()
+            .map(|link| link.elapsed)
+            .sum()
+    }
+}
@@ -0,0 +1,150 @@
+//! Host platform state as reported by the `SNP_PLATFORM_STATUS` command,
+//! used to tie guest reports to the platform they were produced on.
+
+use sev::firmware::host::TcbVersion;
+use crate::report::tcb_from_le_bytes;
+
+/// Size in bytes of the `SNP_PLATFORM_STATUS` response structure.
+pub const PLATFORM_STATUS_LEN: usize = 0x20;
+
+/// Firmware state of the SNP platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlatformState {
+    /// `SNP_INIT` has not been run.
+    Uninit,
+    /// The platform is initialized and can launch guests.
+    Init,
+}
+
+/// The decoded `SNP_PLATFORM_STATUS` response.
+#[derive(Debug, Clone, Copy)]
+pub struct PlatformStatus {
+    /// Firmware API major version.
+    pub api_major: u8,
+    /// Firmware API minor version.
+    pub api_minor: u8,
+    /// Platform state.
+    pub state: PlatformState,
+    /// Whether the RMP table has been initialized.
+    pub is_rmp_initialized: bool,
+    /// Firmware build ID.
+    pub build_id: u32,
+    /// Whether reports have their chip ID masked.
+    pub mask_chip_id: bool,
+    /// Whether reports are signed with the chip key disabled.
+    pub mask_chip_key: bool,
+    /// Whether a VLEK has been installed.
+    pub vlek_enabled: bool,
+    /// Number of guests currently running.
+    pub guest_count: u32,
+    /// TCB the platform is currently running.
+    pub current_tcb: TcbVersion,
+    /// TCB the platform reports to guests, which VCEKs are issued for.
+    pub reported_tcb: TcbVersion,
+}
+
+impl PlatformStatus {
+    /// Parses the `SNP_PLATFORM_STATUS` response written by the firmware.
+    ///
+    /// # Errors
+    /// Returns an error if the buffer is not `PLATFORM_STATUS_LEN` bytes or
+    /// the platform state is unknown.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != PLATFORM_STATUS_LEN {
+            return Err(format!(
+                "Platform status must be {} bytes, got {}",
+                PLATFORM_STATUS_LEN,
+                bytes.len()
+            ));
+        }
+        let word = |offset: usize| {
+            u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap_or([0; 4]))
+        };
+        let tcb = |offset: usize| {
+            tcb_from_le_bytes(bytes[offset..offset + 8].try_into().unwrap_or([0; 8]))
+        };
+
+        let state = match bytes[0x02] {
+            0 => PlatformState::Uninit,
+            1 => PlatformState::Init,
+            other => return Err(format!("Unknown platform state {other}")),
+        };
+        let flags = word(0x08);
+        Ok(PlatformStatus {
+            api_major: bytes[0x00],
+            api_minor: bytes[0x01],
+            state,
+            is_rmp_initialized: bytes[0x03] & 1 != 0,
+            build_id: word(0x04),
+            mask_chip_id: flags & (1 << 0) != 0,
+            mask_chip_key: flags & (1 << 1) != 0,
+            vlek_enabled: flags & (1 << 2) != 0,
+            guest_count: word(0x0c),
+            current_tcb: tcb(0x10),
+            reported_tcb: tcb(0x18),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::ReportPolicy;
+    use crate::test_util::generate_test_chain;
+
+    /// An `SNP_PLATFORM_STATUS` response from an initialized Milan host
+    /// running two guests, with a pending microcode update: the current TCB
+    /// is ahead of the TCB reported to guests.
+    const PLATFORM_STATUS: [u8; PLATFORM_STATUS_LEN] = [
+        0x01, 0x37, 0x01, 0x01, // api 1.55, INIT, RMP initialized
+        0x15, 0x00, 0x00, 0x00, // build_id 21
+        0x06, 0x00, 0x00, 0x00, // mask_chip_key, vlek_enabled
+        0x02, 0x00, 0x00, 0x00, // guest_count 2
+        0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0xd1, // current_tcb
+        0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0xce, // reported_tcb
+    ];
+
+    #[test]
+    fn parses_platform_status_fixture() {
+        let status = PlatformStatus::from_bytes(&PLATFORM_STATUS).unwrap();
+        assert_eq!((status.api_major, status.api_minor), (1, 55));
+        assert_eq!(status.state, PlatformState::Init);
+        assert!(status.is_rmp_initialized);
+        assert_eq!(status.build_id, 21);
+        assert!(!status.mask_chip_id);
+        assert!(status.mask_chip_key);
+        assert!(status.vlek_enabled);
+        assert_eq!(status.guest_count, 2);
+        assert_eq!(
+            (status.current_tcb.bootloader, status.current_tcb.snp, status.current_tcb.microcode),
+            (3, 8, 0xd1)
+        );
+        assert_eq!(
+            (status.reported_tcb.bootloader, status.reported_tcb.snp, status.reported_tcb.microcode),
+            (3, 8, 0xce)
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_platform_status() {
+        assert!(PlatformStatus::from_bytes(&PLATFORM_STATUS[..PLATFORM_STATUS_LEN - 1]).is_err());
+        let mut unknown_state = PLATFORM_STATUS;
+        unknown_state[0x02] = 7;
+        assert!(PlatformStatus::from_bytes(&unknown_state).is_err());
+    }
+
+    #[test]
+    fn policy_ties_report_to_platform_tcb() {
+        let status = PlatformStatus::from_bytes(&PLATFORM_STATUS).unwrap();
+        let (_, mut report) = generate_test_chain().unwrap();
+        let policy = ReportPolicy { platform_tcb: Some(status.reported_tcb), ..Default::default() };
+
+        report.reported_tcb = status.reported_tcb;
+        assert!(policy.violations(&report).is_empty());
+
+        report.reported_tcb = status.current_tcb;
+        let violations = policy.violations(&report);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].contains("platform TCB"), "{violations:?}");
+    }
+}
@@ -0,0 +1,40 @@
+//! Re-exports of the types and traits most callers need for validation.
+//!
+//! ```no_run
+//! use dev_snp_nif::prelude::*;
+//!
+//! fn check(report_json: &[u8], chain: &Chain) -> Result<(), String> {
+//!     let report: AttestationReport = parse_report(report_json).map_err(|e| e.to_string())?;
+//!     ReportPolicy::default().check(&report)?;
+//!     (chain, &report).verify().map_err(|e| e.to_string())
+//! }
+//! ```
+
+pub use sev::certs::snp::ca::Chain as CaChain;
+pub use sev::certs::snp::{Certificate, Chain, Verifiable};
+pub use sev::firmware::guest::AttestationReport;
+pub use sev::firmware::host::TcbVersion;
+
+pub use crate::certs::{CertFormat, CertFormatError, CertType, DerCertificate};
+pub use crate::clock::{Clock, FixedClock, SystemClock};
+pub use crate::product::ProductLine;
+pub use crate::report::{parse_report, parse_report_bytes, ReportParseError, ReportPolicy};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::generate_test_chain;
+
+    #[test]
+    fn validates_a_report_with_prelude_items_alone() {
+        let (chain, report): (Chain, AttestationReport) = generate_test_chain().unwrap();
+        ReportPolicy::default().check(&report).unwrap();
+        (&chain, &report).verify().unwrap();
+
+        let bytes = bincode::serialize(&report).unwrap();
+        let parsed = parse_report_bytes(&bytes).unwrap();
+        let tcb: TcbVersion = parsed.reported_tcb;
+        assert_eq!((tcb.bootloader, tcb.tee, tcb.snp, tcb.microcode), (0, 0, 0, 0));
+        assert_eq!(ProductLine::from_name("milan"), Some(ProductLine::Milan));
+    }
+}
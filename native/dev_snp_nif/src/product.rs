@@ -0,0 +1,77 @@
+use std::fmt;
+
+/// AMD EPYC product lines with distinct SEV-SNP certificate hierarchies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProductLine {
+    Milan,
+    Genoa,
+    Turin,
+}
+
+impl ProductLine {
+    /// All supported product lines.
+    pub const ALL: [ProductLine; 3] = [ProductLine::Milan, ProductLine::Genoa, ProductLine::Turin];
+
+    /// Returns the product name as used by AMD's KDS (e.g., "Milan").
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProductLine::Milan => "Milan",
+            ProductLine::Genoa => "Genoa",
+            ProductLine::Turin => "Turin",
+        }
+    }
+
+    /// Returns the path segment naming the product line in KDS URLs
+    /// (e.g., "Milan" in `/vcek/v1/Milan/cert_chain`).
+    pub fn kds_segment(&self) -> &'static str {
+        self.name()
+    }
+
+    /// Determines the product line from an AMD CA common name such as
+    /// `ARK-Genoa` or `SEV-Milan`, whose suffix after the last `-` names it.
+    pub fn from_cn_suffix(common_name: &str) -> Option<ProductLine> {
+        let (_, suffix) = common_name.rsplit_once('-')?;
+        ProductLine::ALL
+            .into_iter()
+            .find(|product| product.name() == suffix)
+    }
+
+    /// Looks up a product line by its KDS name, ignoring ASCII case.
+    pub fn from_name(name: &str) -> Option<ProductLine> {
+        ProductLine::ALL
+            .into_iter()
+            .find(|product| product.name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl fmt::Display for ProductLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_every_product_line() {
+        for product in ProductLine::ALL {
+            let segment = product.kds_segment();
+            assert_eq!(ProductLine::from_name(segment), Some(product));
+            assert_eq!(ProductLine::from_name(&segment.to_ascii_lowercase()), Some(product));
+            assert_eq!(product.to_string(), segment);
+            for role in ["ARK", "ASK", "SEV"] {
+                assert_eq!(ProductLine::from_cn_suffix(&format!("{role}-{segment}")), Some(product));
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_common_names() {
+        assert_eq!(ProductLine::from_cn_suffix("Milan"), None);
+        assert_eq!(ProductLine::from_cn_suffix("ARK-Naples"), None);
+        assert_eq!(ProductLine::from_cn_suffix("ARK-milan"), None);
+        assert_eq!(ProductLine::from_name("Naples"), None);
+    }
+}
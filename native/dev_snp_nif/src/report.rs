@@ -0,0 +1,1752 @@
+use openssl::bn::BigNum;
+use openssl::ecdsa::EcdsaSig;
+use openssl::pkey::{PKey, Public};
+use openssl::sha::{sha384, sha512};
+use serde_json::{json, Value};
+use sev::certs::snp::ecdsa::Signature;
+use sev::certs::snp::Chain;
+use sev::firmware::host::TcbVersion;
+use sev::firmware::guest::{AttestationReport, GuestPolicy, PlatformInfo};
+use snafu::Snafu;
+use std::collections::HashSet;
+use std::time::Duration;
+use crate::certs::{not_after_unix, not_before_unix};
+use crate::ct::ct_eq;
+use crate::product::ProductLine;
+
+/// Oldest attestation report structure version understood by this module.
+pub const MIN_REPORT_VERSION: u32 = 2;
+/// Newest attestation report structure version understood by this module.
+pub const MAX_REPORT_VERSION: u32 = 3;
+
+/// Size in bytes of a binary attestation report.
+pub const REPORT_LEN: usize = 0x4a0;
+
+/// Offset of the report within the firmware's `MSG_REPORT_RSP` message, after
+/// the `status` and `report_size` words and reserved padding.
+pub const GUEST_RESPONSE_HEADER_LEN: usize = 0x20;
+
+/// Errors raised while parsing an attestation report from untrusted input.
+#[derive(Debug, Snafu)]
+pub enum ReportParseError {
+    /// The binary report is not exactly `REPORT_LEN` bytes long.
+    #[snafu(display("Attestation report must be {expected} bytes, got {actual}"))]
+    WrongLength { expected: usize, actual: usize },
+    /// The report declares a structure version this module cannot parse.
+    #[snafu(display(
+        "Unsupported report version {version}: supported versions are {MIN_REPORT_VERSION} to {MAX_REPORT_VERSION}"
+    ))]
+    UnsupportedVersion { version: u32 },
+    /// A field the specification reserves as zero holds a non-zero value.
+    #[snafu(display("Reserved report field {field} is non-zero"))]
+    ReservedNonZero { field: &'static str },
+    /// The JSON report could not be decoded.
+    #[snafu(display("Failed to parse JSON: {reason}"))]
+    InvalidJson { reason: String },
+    /// The report claims a signature algorithm other than ECDSA P-384 with SHA-384.
+    #[snafu(display("Unsupported report signature algorithm {algo}"))]
+    UnsupportedSignatureAlgorithm { algo: u32 },
+    /// The firmware's report response carries a non-zero status.
+    #[snafu(display("Firmware report request failed with status {status:#x}"))]
+    FirmwareStatus { status: u32 },
+    /// The binary report could not be decoded.
+    #[snafu(display("Failed to decode report: {reason}"))]
+    InvalidBinary { reason: String },
+}
+
+/// CPUID identification fields added to the report in structure version 3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuidInfo {
+    /// Combined family (base family plus extended family).
+    pub family_id: u8,
+    /// Combined model (extended model in the high nibble, base model in the low).
+    pub model_id: u8,
+    pub stepping: u8,
+}
+
+impl CpuidInfo {
+    /// Returns the product line of the attesting CPU, if it is a known
+    /// SEV-SNP capable EPYC generation.
+    pub fn product_line(&self) -> Option<ProductLine> {
+        match (self.family_id, self.model_id) {
+            (0x19, 0x00..=0x0f) => Some(ProductLine::Milan),
+            (0x19, 0x10..=0x1f) | (0x19, 0xa0..=0xaf) => Some(ProductLine::Genoa),
+            // Turin (Zen 5) and Turin dense (Zen 5c); the other family 0x1a
+            // models are client parts without SEV-SNP.
+            (0x1a, 0x00..=0x1f) => Some(ProductLine::Turin),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes a JSON array of integers into a fixed-size byte array.
+/// Missing or malformed values fall back to zeroes.
+fn json_bytes<const N: usize>(value: &Value) -> [u8; N] {
+    value
+        .as_array()
+        .unwrap_or(&vec![])
+        .iter()
+        .map(|v| v.as_u64().unwrap_or(0) as u8)
+        .collect::<Vec<u8>>()
+        .try_into()
+        .unwrap_or([0; N])
+}
+
+/// Decodes a TCB version from its 8-byte little-endian report encoding.
+///
+/// Byte 0 holds the bootloader SVN, byte 1 the TEE SVN, bytes 2..6 are
+/// reserved, byte 6 holds the SNP SVN and byte 7 the microcode SVN.
+pub fn tcb_from_le_bytes(bytes: [u8; 8]) -> TcbVersion {
+    TcbVersion {
+        bootloader: bytes[0],
+        tee: bytes[1],
+        snp: bytes[6],
+        microcode: bytes[7],
+        _reserved: [bytes[2], bytes[3], bytes[4], bytes[5]],
+    }
+}
+
+/// Encodes a TCB version into its 8-byte little-endian report encoding.
+pub fn tcb_to_le_bytes(tcb: &TcbVersion) -> [u8; 8] {
+    [
+        tcb.bootloader,
+        tcb.tee,
+        tcb._reserved[0],
+        tcb._reserved[1],
+        tcb._reserved[2],
+        tcb._reserved[3],
+        tcb.snp,
+        tcb.microcode,
+    ]
+}
+
+/// Decodes a TCB version from the report's raw 64-bit value.
+pub fn tcb_from_u64(raw: u64) -> TcbVersion {
+    tcb_from_le_bytes(raw.to_le_bytes())
+}
+
+/// Encodes a TCB version as the report's raw 64-bit value.
+pub fn tcb_to_u64(tcb: &TcbVersion) -> u64 {
+    u64::from_le_bytes(tcb_to_le_bytes(tcb))
+}
+
+/// Encodes a TCB as the query string KDS expects when fetching a VCEK,
+/// without the leading `?` (e.g., `blSPL=03&teeSPL=00&snpSPL=08&ucodeSPL=115`).
+pub fn tcb_to_kds_query(tcb: &TcbVersion) -> String {
+    format!(
+        "blSPL={:02}&teeSPL={:02}&snpSPL={:02}&ucodeSPL={:02}",
+        tcb.bootloader, tcb.tee, tcb.snp, tcb.microcode
+    )
+}
+
+/// Decodes a KDS VCEK query string, with or without the leading `?`, into a TCB.
+///
+/// # Errors
+/// Returns an error if a parameter is unknown, repeated, missing, or not a
+/// valid SVN.
+pub fn tcb_from_kds_query(query: &str) -> Result<TcbVersion, String> {
+    let mut svns: [Option<u8>; 4] = [None; 4];
+    for pair in query.strip_prefix('?').unwrap_or(query).split('&') {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("Malformed KDS query parameter: {pair}"))?;
+        let index = match key {
+            "blSPL" => 0,
+            "teeSPL" => 1,
+            "snpSPL" => 2,
+            "ucodeSPL" => 3,
+            _ => return Err(format!("Unknown KDS query parameter: {key}")),
+        };
+        let svn = value
+            .parse::<u8>()
+            .map_err(|err| format!("Invalid {key} value {value:?}: {err}"))?;
+        if svns[index].replace(svn).is_some() {
+            return Err(format!("Repeated KDS query parameter: {key}"));
+        }
+    }
+    let [Some(bootloader), Some(tee), Some(snp), Some(microcode)] = svns else {
+        return Err("KDS query must set blSPL, teeSPL, snpSPL and ucodeSPL".to_string());
+    };
+    Ok(TcbVersion {
+        bootloader,
+        tee,
+        snp,
+        microcode,
+        _reserved: [0; 4],
+    })
+}
+
+/// Encodes a chip ID as the HWID path segment of a KDS VCEK URL: 128
+/// lowercase hex digits with no separators.
+pub fn hwid_to_kds_hex(hwid: &[u8; 64]) -> String {
+    hex::encode(hwid)
+}
+
+/// Decodes the HWID path segment of a KDS VCEK URL back into a chip ID.
+///
+/// # Errors
+/// Returns an error unless the input is exactly 128 lowercase hex digits,
+/// the only form KDS accepts.
+pub fn kds_hex_to_hwid(hex_str: &str) -> Result<[u8; 64], String> {
+    if hex_str.len() != 128 {
+        return Err(format!("KDS HWID must be 128 hex digits, got {}", hex_str.len()));
+    }
+    if !hex_str.bytes().all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f')) {
+        return Err("KDS HWID must contain only lowercase hex digits".to_string());
+    }
+    let mut hwid = [0u8; 64];
+    hex::decode_to_slice(hex_str, &mut hwid).map_err(|err| err.to_string())?;
+    Ok(hwid)
+}
+
+/// Reports whether every SVN component of `tcb` is at least that of `baseline`.
+///
+/// This is a component-wise comparison: a TCB that is newer in one component
+/// but older in another does not meet the baseline.
+pub fn tcb_at_least(tcb: &TcbVersion, baseline: &TcbVersion) -> bool {
+    tcb.bootloader >= baseline.bootloader
+        && tcb.tee >= baseline.tee
+        && tcb.snp >= baseline.snp
+        && tcb.microcode >= baseline.microcode
+}
+
+/// Decodes a JSON TCB field into a `TcbVersion`.
+/// Accepts either an object of named components or the raw 64-bit value.
+fn json_tcb(value: &Value) -> TcbVersion {
+    if let Some(raw) = value.as_u64() {
+        return tcb_from_u64(raw);
+    }
+    TcbVersion {
+        bootloader: value["bootloader"].as_u64().unwrap_or(0) as u8,
+        tee: value["tee"].as_u64().unwrap_or(0) as u8,
+        snp: value["snp"].as_u64().unwrap_or(0) as u8,
+        microcode: value["microcode"].as_u64().unwrap_or(0) as u8,
+        _reserved: [0; 4],
+    }
+}
+
+/// Ensures a report structure version is one this module knows how to parse.
+///
+/// # Errors
+/// Returns `ReportParseError::UnsupportedVersion` for versions older than
+/// `MIN_REPORT_VERSION` (including a zeroed field) or newer than
+/// `MAX_REPORT_VERSION`, rather than silently misparsing the layout.
+pub fn check_report_version(version: u32) -> Result<(), ReportParseError> {
+    if !(MIN_REPORT_VERSION..=MAX_REPORT_VERSION).contains(&version) {
+        return Err(ReportParseError::UnsupportedVersion { version });
+    }
+    Ok(())
+}
+
+/// Ensures the fields reserved by the specification for the report's version are zero.
+///
+/// # Errors
+/// Returns `ReportParseError::ReservedNonZero` naming the first offending field.
+pub fn check_reserved_fields(report: &AttestationReport) -> Result<(), ReportParseError> {
+    // Version 3 reports use the first three bytes of this region for CPUID.
+    let reserved_1 = if report.version >= 3 {
+        &report._reserved_1[3..]
+    } else {
+        &report._reserved_1[..]
+    };
+    let checks: [(&'static str, bool); 6] = [
+        ("_reserved_0", report._reserved_0 == 0),
+        ("_reserved_1", reserved_1.iter().all(|&b| b == 0)),
+        ("_reserved_2", report._reserved_2 == 0),
+        ("_reserved_3", report._reserved_3 == 0),
+        ("_reserved_4", report._reserved_4.iter().all(|&b| b == 0)),
+        ("signature._reserved", report.signature._reserved.iter().all(|&b| b == 0)),
+    ];
+    match checks.into_iter().find(|&(_, zero)| !zero) {
+        Some((field, _)) => Err(ReportParseError::ReservedNonZero { field }),
+        None => Ok(()),
+    }
+}
+
+/// Signature algorithm identifier for ECDSA P-384 with SHA-384, the only
+/// algorithm SNP firmware signs reports with.
+pub const SIG_ALGO_ECDSA_P384_SHA384: u32 = 1;
+
+/// Offset of the `sig_algo` field in a binary report.
+const SIG_ALGO_OFFSET: usize = 0x34;
+
+/// Runs only the cheap structural checks on a binary report (length,
+/// version and signature algorithm), so malformed input can be turned away
+/// before any decoding or signature verification is spent on it.
+///
+/// # Returns
+/// The first problem found, or `None` if the input passes the checks.
+pub fn quick_reject(bytes: &[u8]) -> Option<ReportParseError> {
+    if bytes.len() != REPORT_LEN {
+        return Some(ReportParseError::WrongLength {
+            expected: REPORT_LEN,
+            actual: bytes.len(),
+        });
+    }
+    let word = |offset: usize| {
+        u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+    };
+    if let Err(err) = check_report_version(word(0)) {
+        return Some(err);
+    }
+    let algo = word(SIG_ALGO_OFFSET);
+    if algo != SIG_ALGO_ECDSA_P384_SHA384 {
+        return Some(ReportParseError::UnsupportedSignatureAlgorithm { algo });
+    }
+    None
+}
+
+/// Parses a binary attestation report, as returned by the firmware.
+///
+/// Unlike a bare deserialization, this never reads past a short buffer and
+/// rejects inputs that are not exactly one report long.
+///
+/// # Errors
+/// Returns `WrongLength` for inputs of the wrong size, `UnsupportedVersion`
+/// for unknown layouts, `UnsupportedSignatureAlgorithm` for reports not
+/// signed with ECDSA P-384, and `ReservedNonZero` if a reserved field is set.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(len = bytes.len()), err))]
+pub fn parse_report_bytes(bytes: &[u8]) -> Result<AttestationReport, ReportParseError> {
+    if let Some(err) = quick_reject(bytes) {
+        return Err(err);
+    }
+
+    let report: AttestationReport = bincode::deserialize(bytes)
+        .map_err(|err| ReportParseError::InvalidBinary { reason: err.to_string() })?;
+    check_reserved_fields(&report)?;
+    Ok(report)
+}
+
+/// Extracts and parses the report from the `MSG_REPORT_RSP` structure the
+/// guest reads back from `/dev/sev-guest`.
+///
+/// # Errors
+/// Returns `FirmwareStatus` if the firmware reported a failure, `WrongLength`
+/// if the declared `report_size` is not `REPORT_LEN` or the response is too
+/// short to hold it, and otherwise any error from `parse_report_bytes`.
+pub fn parse_guest_response(raw: &[u8]) -> Result<AttestationReport, ReportParseError> {
+    if raw.len() < GUEST_RESPONSE_HEADER_LEN {
+        return Err(ReportParseError::WrongLength {
+            expected: GUEST_RESPONSE_HEADER_LEN + REPORT_LEN,
+            actual: raw.len(),
+        });
+    }
+    let status = u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]);
+    if status != 0 {
+        return Err(ReportParseError::FirmwareStatus { status });
+    }
+    let report_size = u32::from_le_bytes([raw[4], raw[5], raw[6], raw[7]]) as usize;
+    if report_size != REPORT_LEN {
+        return Err(ReportParseError::WrongLength {
+            expected: REPORT_LEN,
+            actual: report_size,
+        });
+    }
+    let report = raw
+        .get(GUEST_RESPONSE_HEADER_LEN..GUEST_RESPONSE_HEADER_LEN + REPORT_LEN)
+        .ok_or(ReportParseError::WrongLength {
+            expected: GUEST_RESPONSE_HEADER_LEN + REPORT_LEN,
+            actual: raw.len(),
+        })?;
+    parse_report_bytes(report)
+}
+
+/// Parses a JSON-serialized attestation report into an `AttestationReport`.
+///
+/// # Arguments
+/// * `json` - The attestation report as produced by `generate_attestation_report`.
+///
+/// # Returns
+/// The parsed report, with each field individually decoded.
+///
+/// # Errors
+/// Returns an error if the input is not valid JSON or if the report declares
+/// a structure version outside the supported range.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, err))]
+pub fn parse_report(json: &[u8]) -> Result<AttestationReport, ReportParseError> {
+    // Step 1: Parse the report JSON into a serde Value object.
+    let json_data = serde_json::from_slice::<Value>(json)
+        .map_err(|err| ReportParseError::InvalidJson { reason: err.to_string() })?;
+
+    // Step 2: Reject layouts we do not understand before decoding any fields.
+    let version = json_data["version"].as_u64().unwrap_or(0) as u32;
+    check_report_version(version)?;
+
+    // Step 3: Map JSON fields to the AttestationReport struct.
+    // Each field is individually parsed to ensure type safety.
+    Ok(AttestationReport {
+        version,
+        guest_svn: json_data["guest_svn"].as_u64().unwrap_or(0) as u32,
+        policy: GuestPolicy(json_data["policy"].as_u64().unwrap_or(0)),
+        family_id: json_bytes(&json_data["family_id"]),
+        image_id: json_bytes(&json_data["image_id"]),
+        vmpl: json_data["vmpl"].as_u64().unwrap_or(0) as u32,
+        sig_algo: json_data["sig_algo"].as_u64().unwrap_or(0) as u32,
+        current_tcb: json_tcb(&json_data["current_tcb"]),
+        plat_info: PlatformInfo(json_data["plat_info"].as_u64().unwrap_or(0)),
+        _author_key_en: json_data["_author_key_en"].as_u64().unwrap_or(0) as u32,
+        _reserved_0: json_data["_reserved_0"].as_u64().unwrap_or(0) as u32,
+        report_data: json_bytes(&json_data["report_data"]),
+        measurement: json_bytes(&json_data["measurement"]),
+        host_data: json_bytes(&json_data["host_data"]),
+        id_key_digest: json_bytes(&json_data["id_key_digest"]),
+        author_key_digest: json_bytes(&json_data["author_key_digest"]),
+        report_id: json_bytes(&json_data["report_id"]),
+        report_id_ma: json_bytes(&json_data["report_id_ma"]),
+        reported_tcb: json_tcb(&json_data["reported_tcb"]),
+        // Version 3 reports carry the CPUID fields in the first bytes of this
+        // region, so it must be preserved for the signature to verify.
+        _reserved_1: json_bytes(&json_data["_reserved_1"]),
+        chip_id: json_bytes(&json_data["chip_id"]),
+        committed_tcb: json_tcb(&json_data["committed_tcb"]),
+        current_build: json_data["current_build"].as_u64().unwrap_or(0) as u8,
+        current_minor: json_data["current_minor"].as_u64().unwrap_or(0) as u8,
+        current_major: json_data["current_major"].as_u64().unwrap_or(0) as u8,
+        _reserved_2: json_data["_reserved_2"].as_u64().unwrap_or(0) as u8,
+        committed_build: json_data["committed_build"].as_u64().unwrap_or(0) as u8,
+        committed_minor: json_data["committed_minor"].as_u64().unwrap_or(0) as u8,
+        committed_major: json_data["committed_major"].as_u64().unwrap_or(0) as u8,
+        _reserved_3: json_data["_reserved_3"].as_u64().unwrap_or(0) as u8,
+        launch_tcb: json_tcb(&json_data["launch_tcb"]),
+        // Reserved regions are decoded rather than zeroed, so that policies
+        // enforcing them see what the report actually carried.
+        _reserved_4: json_bytes(&json_data["_reserved_4"]),
+        signature: Signature {
+            r: json_bytes(&json_data["signature"]["r"]),
+            s: json_bytes(&json_data["signature"]["s"]),
+            _reserved: json_bytes(&json_data["signature"]["_reserved"]),
+        },
+    })
+}
+
+/// Returns the structure version declared by the report.
+pub fn report_version(report: &AttestationReport) -> u32 {
+    report.version
+}
+
+/// Returns the build number of the firmware that produced the report.
+pub fn current_build(report: &AttestationReport) -> u32 {
+    report.current_build as u32
+}
+
+/// Returns the CPUID identification fields of a version 3 report.
+///
+/// # Returns
+/// `None` for version 2 reports, which do not carry these fields.
+pub fn report_cpuid(report: &AttestationReport) -> Option<CpuidInfo> {
+    if report_version(report) < 3 {
+        return None;
+    }
+    Some(CpuidInfo {
+        family_id: report._reserved_1[0],
+        model_id: report._reserved_1[1],
+        stepping: report._reserved_1[2],
+    })
+}
+
+/// Determines the product line of the CPU that produced the report.
+///
+/// Version 3 reports identify the CPU themselves; version 2 reports do not,
+/// so the caller has to name the product line.
+///
+/// # Arguments
+/// * `report` - The attestation report.
+/// * `fallback` - The product line to assume for reports without CPUID fields.
+///
+/// # Errors
+/// Returns an error if the report's CPUID names no known product line, or
+/// the report carries no CPUID fields and no fallback was given.
+pub fn report_product_line(
+    report: &AttestationReport,
+    fallback: Option<ProductLine>,
+) -> Result<ProductLine, String> {
+    match report_cpuid(report) {
+        Some(cpuid) => cpuid.product_line().ok_or_else(|| {
+            format!(
+                "CPU family {:#x} model {:#x} is not a known SEV-SNP product line",
+                cpuid.family_id, cpuid.model_id
+            )
+        }),
+        None => fallback.ok_or_else(|| {
+            format!(
+                "Version {} reports do not identify the CPU; the product line must be given",
+                report_version(report)
+            )
+        }),
+    }
+}
+
+/// Returns the TCB the firmware is currently running at.
+pub fn current_tcb(report: &AttestationReport) -> TcbVersion {
+    report.current_tcb
+}
+
+/// Returns the TCB the firmware has committed to; the platform cannot be
+/// rolled back below this version.
+pub fn committed_tcb(report: &AttestationReport) -> TcbVersion {
+    report.committed_tcb
+}
+
+/// Returns the TCB the guest asked to be reported, which selects the VCEK.
+pub fn reported_tcb(report: &AttestationReport) -> TcbVersion {
+    report.reported_tcb
+}
+
+/// Returns the TCB that was current when the guest was launched.
+pub fn launch_tcb(report: &AttestationReport) -> TcbVersion {
+    report.launch_tcb
+}
+
+/// Returns the raw 64-bit guest policy the guest was launched with.
+pub fn policy_raw(report: &AttestationReport) -> u64 {
+    report.policy.0
+}
+
+/// Returns the guest security version number supplied at launch.
+pub fn guest_svn(report: &AttestationReport) -> u32 {
+    report.guest_svn
+}
+
+/// Returns the VM Privilege Level the report was requested at.
+pub fn vmpl(report: &AttestationReport) -> u32 {
+    report.vmpl
+}
+
+/// Returns the guest family ID supplied at launch.
+pub fn family_id(report: &AttestationReport) -> [u8; 16] {
+    report.family_id
+}
+
+/// Returns the guest image ID supplied at launch.
+pub fn image_id(report: &AttestationReport) -> [u8; 16] {
+    report.image_id
+}
+
+/// Returns the ID the firmware assigned to the guest at launch, which stays
+/// the same across every report the guest requests.
+pub fn report_id(report: &AttestationReport) -> [u8; 32] {
+    report.report_id
+}
+
+/// Returns the report ID of the guest's migration agent; all ones if the
+/// guest has no migration agent.
+pub fn report_id_ma(report: &AttestationReport) -> [u8; 32] {
+    report.report_id_ma
+}
+
+/// Returns the data the host bound into the guest at launch (e.g., a digest
+/// of the deployment's configuration).
+pub fn host_data(report: &AttestationReport) -> [u8; 32] {
+    report.host_data
+}
+
+/// Reports whether all the reports were produced by the same physical chip.
+///
+/// Chip IDs are compared in constant time and every report is compared even
+/// after a mismatch. An empty or single-report slice trivially matches.
+pub fn same_chip(reports: &[AttestationReport]) -> bool {
+    let Some(first) = reports.first() else {
+        return true;
+    };
+    reports[1..]
+        .iter()
+        .fold(true, |same, report| ct_eq(&first.chip_id, &report.chip_id) & same)
+}
+
+/// The key that signed an attestation report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigningKeyKind {
+    /// Versioned Chip Endorsement Key, fetched per chip from KDS.
+    Vcek,
+    /// Versioned Loaded Endorsement Key, provisioned by the cloud provider.
+    Vlek,
+    /// The report is unsigned.
+    None,
+}
+
+/// Decodes which key signed the report from the KEY_INFO field
+/// (bits 4:2 of the word at offset 0x48: 0 = VCEK, 1 = VLEK, 7 = none).
+///
+/// # Errors
+/// Returns an error for reserved signing key encodings.
+pub fn signing_key(report: &AttestationReport) -> Result<SigningKeyKind, String> {
+    match (report._author_key_en >> 2) & 0b111 {
+        0 => Ok(SigningKeyKind::Vcek),
+        1 => Ok(SigningKeyKind::Vlek),
+        7 => Ok(SigningKeyKind::None),
+        other => Err(format!("Reserved signing key encoding {other} in report")),
+    }
+}
+
+/// Host platform features recorded in the report's `plat_info` field.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlatformInfoFlags {
+    /// Simultaneous multithreading is enabled on the host.
+    pub smt_enabled: bool,
+    /// Transparent SME (memory encryption) is enabled on the host.
+    pub tsme_enabled: bool,
+}
+
+/// Decodes the host platform features recorded in the report.
+pub fn platform_info(report: &AttestationReport) -> PlatformInfoFlags {
+    let raw = report.plat_info.0;
+    PlatformInfoFlags {
+        smt_enabled: raw & (1 << 0) != 0,
+        tsme_enabled: raw & (1 << 1) != 0,
+    }
+}
+
+/// Launch policy fields recorded in the report's `policy` field.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GuestPolicyFlags {
+    /// Minimum firmware ABI minor version required by the guest.
+    pub abi_minor: u8,
+    /// Minimum firmware ABI major version required by the guest.
+    pub abi_major: u8,
+    /// SMT is allowed on the host.
+    pub smt_allowed: bool,
+    /// Association with a migration agent is allowed.
+    pub migrate_ma_allowed: bool,
+    /// Debugging of the guest is allowed.
+    pub debug_allowed: bool,
+    /// The guest may only be activated on a single socket.
+    pub single_socket_required: bool,
+}
+
+/// Decodes the guest launch policy recorded in the report.
+pub fn guest_policy(report: &AttestationReport) -> GuestPolicyFlags {
+    let raw = report.policy.0;
+    GuestPolicyFlags {
+        abi_minor: (raw & 0xff) as u8,
+        abi_major: ((raw >> 8) & 0xff) as u8,
+        smt_allowed: raw & (1 << 16) != 0,
+        migrate_ma_allowed: raw & (1 << 18) != 0,
+        debug_allowed: raw & (1 << 19) != 0,
+        single_socket_required: raw & (1 << 20) != 0,
+    }
+}
+
+/// Operator policy that a report must satisfy beyond a valid signature.
+/// The default policy imposes no additional constraints.
+#[derive(Debug, Clone, Default)]
+pub struct ReportPolicy {
+    /// Reject reports from hosts without TSME enabled.
+    pub require_tsme: bool,
+    /// Reject reports from hosts with SMT enabled.
+    pub forbid_smt: bool,
+    /// Exact raw guest policy the guest must have been launched with, the
+    /// strictest possible binding of the launch policy.
+    pub expected_policy: Option<u64>,
+    /// Minimum guest ABI as `(major, minor)` the launch policy must require.
+    pub min_abi: Option<(u8, u8)>,
+    /// Reject guests whose launch policy allows a migration agent.
+    pub forbid_migration_agent: bool,
+    /// Required guest family ID, binding trust to a guest image lineage.
+    pub family_id: Option<[u8; 16]>,
+    /// Required guest image ID.
+    pub image_id: Option<[u8; 16]>,
+    /// Minimum guest SVN, rejecting guests running an outdated image.
+    pub min_guest_svn: Option<u32>,
+    /// Required host data, binding trust to what the host declared at launch.
+    pub host_data: Option<[u8; 32]>,
+    /// Required launch measurement.
+    pub measurement: Option<[u8; 48]>,
+    /// VMPL the report must have been requested at (e.g., 0 for VMPL0 only).
+    pub required_vmpl: Option<u32>,
+    /// Reject reports whose committed TCB is below the TCB the guest launched
+    /// with, which indicates the host rolled back firmware.
+    pub forbid_rollback: bool,
+    /// Reject reports with any non-zero reserved region. Binary reports are
+    /// always held to this when parsed; this extends it to reports decoded
+    /// from JSON or constructed by the caller.
+    pub strict_reserved: bool,
+    /// Reported TCB the report must carry exactly, e.g. the platform's
+    /// `PlatformStatus::reported_tcb`, tying the report to that host's state.
+    pub platform_tcb: Option<TcbVersion>,
+    /// Report IDs already redeemed or revoked, for one-time-use attestation;
+    /// a report carrying any of them is rejected. A guest's report ID is
+    /// fixed at launch, so this rejects every later report from that guest.
+    /// Empty imposes no constraint.
+    pub seen_report_ids: HashSet<[u8; 32]>,
+    /// Longest validity period (`notBefore` to `notAfter`) any certificate in
+    /// the chain may have; AMD's ARKs are issued for 25 years.
+    pub max_validity: Option<Duration>,
+}
+
+impl ReportPolicy {
+    /// Checks the report against this policy.
+    ///
+    /// # Errors
+    /// Returns an error describing the first constraint the report violates.
+    pub fn check(&self, report: &AttestationReport) -> Result<(), String> {
+        match self.violations(report).into_iter().next() {
+            Some(violation) => Err(violation),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns every constraint of this policy the report violates, in the
+    /// order `check` evaluates them, for audits that want all failures at once.
+    pub fn violations(&self, report: &AttestationReport) -> Vec<String> {
+        let mut violations = Vec::new();
+        let plat = platform_info(report);
+        if self.require_tsme && !plat.tsme_enabled {
+            violations.push("Policy requires TSME but the host does not enable it".to_string());
+        }
+        if self.forbid_smt && plat.smt_enabled {
+            violations.push("Policy forbids SMT but the host enables it".to_string());
+        }
+        if let Some(expected) = self.expected_policy {
+            if policy_raw(report) != expected {
+                violations.push(format!(
+                    "Guest policy {:#018x} does not match the expected policy {:#018x}",
+                    policy_raw(report),
+                    expected
+                ));
+            }
+        }
+        let guest = guest_policy(report);
+        if let Some((major, minor)) = self.min_abi {
+            if (guest.abi_major, guest.abi_minor) < (major, minor) {
+                violations.push(format!(
+                    "Guest ABI {}.{} is below the required minimum {}.{}",
+                    guest.abi_major, guest.abi_minor, major, minor
+                ));
+            }
+        }
+        if self.forbid_migration_agent && guest.migrate_ma_allowed {
+            violations.push("Policy forbids migration agents but the guest allows one".to_string());
+        }
+        if let Some(expected) = self.family_id {
+            if !ct_eq(&expected, &report.family_id) {
+                violations.push("Report family ID does not match the policy".to_string());
+            }
+        }
+        if let Some(expected) = self.image_id {
+            if !ct_eq(&expected, &report.image_id) {
+                violations.push("Report image ID does not match the policy".to_string());
+            }
+        }
+        if let Some(min) = self.min_guest_svn {
+            if guest_svn(report) < min {
+                violations.push(format!(
+                    "Guest SVN {} is below the required minimum {}",
+                    guest_svn(report),
+                    min
+                ));
+            }
+        }
+        if let Some(expected) = self.measurement {
+            if !ct_eq(&expected, &report.measurement) {
+                violations.push("Report measurement does not match the policy".to_string());
+            }
+        }
+        if let Some(expected) = self.host_data {
+            if !ct_eq(&expected, &host_data(report)) {
+                violations.push("Report host data does not match the policy".to_string());
+            }
+        }
+        if let Some(required) = self.required_vmpl {
+            if vmpl(report) != required {
+                violations.push(format!(
+                    "Report was generated at VMPL{} but the policy requires VMPL{}",
+                    vmpl(report),
+                    required
+                ));
+            }
+        }
+        if self.strict_reserved {
+            if let Err(err) = check_reserved_fields(report) {
+                violations.push(err.to_string());
+            }
+        }
+        if self.forbid_rollback && !tcb_at_least(&report.committed_tcb, &report.launch_tcb) {
+            violations.push(format!(
+                "Committed TCB {:?} is below the launch TCB {:?}: firmware rollback suspected",
+                report.committed_tcb, report.launch_tcb
+            ));
+        }
+        if self.seen_report_ids.contains(&report.report_id) {
+            violations.push(format!(
+                "Report ID {} has already been seen",
+                hex::encode(report.report_id)
+            ));
+        }
+        if let Some(expected) = self.platform_tcb {
+            if tcb_to_u64(&report.reported_tcb) != tcb_to_u64(&expected) {
+                violations.push(format!(
+                    "Reported TCB {:?} does not match the platform TCB {:?}",
+                    report.reported_tcb, expected
+                ));
+            }
+        }
+        violations
+    }
+
+    /// Checks the chain that signed the report against this policy.
+    ///
+    /// # Errors
+    /// Returns an error naming the first certificate that violates the policy.
+    pub fn check_chain(&self, chain: &Chain) -> Result<(), String> {
+        if let Some(max_validity) = self.max_validity {
+            let max_secs = i64::try_from(max_validity.as_secs()).unwrap_or(i64::MAX);
+            for (name, cert) in [("ARK", &chain.ca.ark), ("ASK", &chain.ca.ask), ("VCEK", &chain.vek)] {
+                let read_err = |err: Box<dyn std::error::Error>| format!("Failed to read {name} validity: {err}");
+                let span = not_after_unix(cert).map_err(read_err)? - not_before_unix(cert).map_err(read_err)?;
+                if span > max_secs {
+                    return Err(format!(
+                        "{name} is valid for {span} seconds, longer than the policy maximum of {max_secs}"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Encodes a TCB version as a structured JSON object.
+fn tcb_to_json(tcb: &TcbVersion) -> Value {
+    json!({
+        "bootloader": tcb.bootloader,
+        "tee": tcb.tee,
+        "snp": tcb.snp,
+        "microcode": tcb.microcode,
+        "raw": tcb_to_u64(tcb),
+    })
+}
+
+/// Exports the decoded report as JSON for logging or external policy engines.
+///
+/// Unlike the serde encoding used between the NIFs, this schema is meant for
+/// consumers outside the crate and is kept stable:
+/// - byte-array fields (`family_id`, `image_id`, `report_data`, `measurement`,
+///   `host_data`, `id_key_digest`, `author_key_digest`, `report_id`,
+///   `report_id_ma`, `chip_id`, `signature.r`, `signature.s`) are lowercase hex strings;
+/// - TCB fields (`current_tcb`, `reported_tcb`, `committed_tcb`, `launch_tcb`)
+///   are objects with `bootloader`, `tee`, `snp`, `microcode` and the `raw` 64-bit value;
+/// - `policy` and `plat_info` carry both the `raw` value and their decoded flags;
+/// - firmware versions are `"major.minor.build"` strings;
+/// - `cpuid` is an object with `family_id`, `model_id` and `stepping` for
+///   version 3 reports and `null` otherwise;
+/// - reserved fields are omitted.
+pub fn report_to_json(report: &AttestationReport) -> Value {
+    let policy = guest_policy(report);
+    let plat = platform_info(report);
+    json!({
+        "version": report.version,
+        "guest_svn": report.guest_svn,
+        "policy": {
+            "raw": report.policy.0,
+            "abi_major": policy.abi_major,
+            "abi_minor": policy.abi_minor,
+            "smt_allowed": policy.smt_allowed,
+            "migrate_ma_allowed": policy.migrate_ma_allowed,
+            "debug_allowed": policy.debug_allowed,
+            "single_socket_required": policy.single_socket_required,
+        },
+        "family_id": hex::encode(report.family_id),
+        "image_id": hex::encode(report.image_id),
+        "vmpl": report.vmpl,
+        "sig_algo": report.sig_algo,
+        "current_tcb": tcb_to_json(&report.current_tcb),
+        "plat_info": {
+            "raw": report.plat_info.0,
+            "smt_enabled": plat.smt_enabled,
+            "tsme_enabled": plat.tsme_enabled,
+        },
+        "report_data": hex::encode(report.report_data),
+        "measurement": hex::encode(report.measurement),
+        "host_data": hex::encode(report.host_data),
+        "id_key_digest": hex::encode(report.id_key_digest),
+        "author_key_digest": hex::encode(report.author_key_digest),
+        "report_id": hex::encode(report.report_id),
+        "report_id_ma": hex::encode(report.report_id_ma),
+        "reported_tcb": tcb_to_json(&report.reported_tcb),
+        "chip_id": hex::encode(report.chip_id),
+        "committed_tcb": tcb_to_json(&report.committed_tcb),
+        "current_version": format!(
+            "{}.{}.{}",
+            report.current_major, report.current_minor, report.current_build
+        ),
+        "committed_version": format!(
+            "{}.{}.{}",
+            report.committed_major, report.committed_minor, report.committed_build
+        ),
+        "launch_tcb": tcb_to_json(&report.launch_tcb),
+        "cpuid": report_cpuid(report).map(|cpuid| json!({
+            "family_id": cpuid.family_id,
+            "model_id": cpuid.model_id,
+            "stepping": cpuid.stepping,
+        })),
+        "signature": {
+            "r": hex::encode(report.signature.r),
+            "s": hex::encode(report.signature.s),
+        },
+    })
+}
+
+/// Length of the report prefix covered by the signature (bytes 0..0x2A0).
+pub const SIGNED_REGION_LEN: usize = 0x2a0;
+
+/// Returns the length of the report prefix the signature covers for a
+/// structure version.
+///
+/// Version 3 only assigned fields inside the previously reserved areas, so
+/// both supported versions sign bytes 0..0x2A0 and place the signature right
+/// after it; the lookup is keyed on the version so a future layout cannot be
+/// verified over the wrong region by accident.
+///
+/// # Errors
+/// Returns `ReportParseError::UnsupportedVersion` for versions outside the
+/// supported range.
+pub fn signed_region_len(version: u32) -> Result<usize, ReportParseError> {
+    match version {
+        2 | 3 => Ok(SIGNED_REGION_LEN),
+        _ => Err(ReportParseError::UnsupportedVersion { version }),
+    }
+}
+
+/// Returns the exact bytes the report signature covers, for integrators that
+/// perform the signature check themselves (e.g., in an HSM).
+///
+/// # Errors
+/// Returns an error if the report declares an unsupported version or cannot
+/// be serialized to its binary layout.
+pub fn signed_bytes(report: &AttestationReport) -> Result<Vec<u8>, String> {
+    let len = signed_region_len(report.version).map_err(|err| err.to_string())?;
+    let raw = bincode::serialize(report)
+        .map_err(|err| format!("Failed to serialize attestation report: {:?}", err))?;
+    raw.get(..len)
+        .map(|region| region.to_vec())
+        .ok_or_else(|| "Serialized attestation report is shorter than the signed region".to_string())
+}
+
+/// Returns the raw little-endian `r` and `s` components of the report signature.
+pub fn signature_raw(report: &AttestationReport) -> (&[u8], &[u8]) {
+    (&report.signature.r, &report.signature.s)
+}
+
+/// Converts a 72-byte little-endian signature component into a 48-byte
+/// big-endian P-384 scalar.
+#[cfg(feature = "pure-rust")]
+fn le_component_to_p384(component: &[u8]) -> Result<[u8; 48], String> {
+    let (low, high) = component.split_at(48);
+    if high.iter().any(|&b| b != 0) {
+        return Err("Signature component does not fit a P-384 scalar".to_string());
+    }
+    let mut be = [0u8; 48];
+    be.copy_from_slice(low);
+    be.reverse();
+    Ok(be)
+}
+
+/// Returns the report signature as a RustCrypto `p384` signature, for
+/// verifiers that do not use OpenSSL.
+///
+/// # Errors
+/// Returns an error if a component exceeds 48 bytes or is not a valid scalar.
+#[cfg(feature = "pure-rust")]
+pub fn ecdsa_signature(report: &AttestationReport) -> Result<p384::ecdsa::Signature, String> {
+    let mut rs = [0u8; 96];
+    rs[..48].copy_from_slice(&le_component_to_p384(&report.signature.r)?);
+    rs[48..].copy_from_slice(&le_component_to_p384(&report.signature.s)?);
+    p384::ecdsa::Signature::from_slice(&rs).map_err(|err| format!("Invalid report signature: {err}"))
+}
+
+/// Converts a little-endian signature component into an OpenSSL big number.
+fn le_component_to_bignum(component: &[u8]) -> Result<BigNum, String> {
+    let mut be = component.to_vec();
+    be.reverse();
+    BigNum::from_slice(&be).map_err(|err| format!("Invalid signature component: {:?}", err))
+}
+
+/// A report signature in either of the encodings callers hand us.
+#[derive(Debug, Clone)]
+pub enum SignatureInput {
+    /// The report's own layout: 72-byte little-endian `r` followed by 72-byte `s`.
+    RawRS([u8; 144]),
+    /// A DER-encoded ECDSA-Sig-Value.
+    Der(Vec<u8>),
+}
+
+impl SignatureInput {
+    /// Normalizes the signature into OpenSSL's representation.
+    fn to_ecdsa_sig(&self) -> Result<EcdsaSig, String> {
+        match self {
+            SignatureInput::RawRS(raw) => EcdsaSig::from_private_components(
+                le_component_to_bignum(&raw[..72])?,
+                le_component_to_bignum(&raw[72..])?,
+            )
+            .map_err(|err| format!("Invalid report signature: {:?}", err)),
+            SignatureInput::Der(der) => EcdsaSig::from_der(der)
+                .map_err(|err| format!("Invalid DER report signature: {:?}", err)),
+        }
+    }
+}
+
+/// Verifies a report signature over the signed report bytes with an ECDSA
+/// P-384 public key, accepting the signature in raw or DER form.
+///
+/// # Arguments
+/// * `public_key` - The ECDSA P-384 public key of the VCEK.
+/// * `signed_bytes` - The signed region of the report (see `signed_bytes`).
+/// * `signature` - The signature, raw `r || s` or DER.
+///
+/// # Errors
+/// Returns an error if the key is not an EC key, the signature is malformed,
+/// or it does not verify.
+pub fn verify_report_with_signature(
+    public_key: &PKey<Public>,
+    signed_bytes: &[u8],
+    signature: &SignatureInput,
+) -> Result<(), String> {
+    let ec_key = public_key
+        .ec_key()
+        .map_err(|err| format!("Public key is not an EC key: {:?}", err))?;
+    let signature = signature.to_ecdsa_sig()?;
+    let digest = sha384(signed_bytes);
+    match signature.verify(&digest, &ec_key) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err("Report signature does not match the provided public key".to_string()),
+        Err(err) => Err(format!("Failed to verify report signature: {:?}", err)),
+    }
+}
+
+/// Verifies the report signature directly against a pinned VCEK public key,
+/// without requiring the VCEK certificate or its chain.
+///
+/// The caller is responsible for having validated the key beforehand.
+///
+/// # Arguments
+/// * `public_key` - The ECDSA P-384 public key of the VCEK.
+/// * `report` - The attestation report to verify.
+///
+/// # Errors
+/// Returns an error if the key is not an EC key or the signature does not verify.
+pub fn verify_report_with_key(
+    public_key: &PKey<Public>,
+    report: &AttestationReport,
+) -> Result<(), String> {
+    let mut raw = [0u8; 144];
+    raw[..72].copy_from_slice(&report.signature.r);
+    raw[72..].copy_from_slice(&report.signature.s);
+    verify_report_with_signature(public_key, &signed_bytes(report)?, &SignatureInput::RawRS(raw))
+}
+
+/// Computes the `report_data` that binds a session public key to a report:
+/// the SHA-512 digest of the key's DER encoding.
+///
+/// # Arguments
+/// * `pubkey_der` - The DER-encoded public key being bound.
+pub fn bind_key_report_data(pubkey_der: &[u8]) -> [u8; 64] {
+    sha512(pubkey_der)
+}
+
+/// Reports whether the report's `report_data` binds the given public key.
+///
+/// # Errors
+/// Returns an error if the key is empty, as an empty key binds nothing.
+pub fn verify_bound_key(report: &AttestationReport, pubkey_der: &[u8]) -> Result<bool, String> {
+    if pubkey_der.is_empty() {
+        return Err("Cannot verify binding of an empty public key".to_string());
+    }
+    Ok(ct_eq(&bind_key_report_data(pubkey_der), &report.report_data))
+}
+
+/// Computes the `report_data` that binds a report to a TLS session: the
+/// SHA-512 digest of the session's keying-material exporter value.
+///
+/// # Arguments
+/// * `exporter` - The TLS exporter output (RFC 5705 / RFC 8446 §7.5).
+pub fn bind_channel_report_data(exporter: &[u8]) -> [u8; 64] {
+    sha512(exporter)
+}
+
+/// Reports whether the report's `report_data` binds the given TLS exporter
+/// value, i.e. whether the report was produced for this live session rather
+/// than relayed from another.
+///
+/// # Errors
+/// Returns an error if the exporter value is empty, as it binds nothing.
+pub fn verify_channel_binding(report: &AttestationReport, exporter: &[u8]) -> Result<bool, String> {
+    if exporter.is_empty() {
+        return Err("Cannot verify binding of an empty TLS exporter value".to_string());
+    }
+    Ok(ct_eq(&bind_channel_report_data(exporter), &report.report_data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{generate_key, generate_test_chain, mint_valid_for, sign_report};
+    use openssl::x509::extension::{BasicConstraints, KeyUsage};
+
+    /// A version 2 report captured from a Milan guest.
+    const V2_REPORT_JSON: &[u8] = include_bytes!("../../../test/snp-attestation.json");
+
+    /// Returns the fixture report as JSON with `edit` applied.
+    fn edited_json(edit: impl FnOnce(&mut Value)) -> Vec<u8> {
+        let mut json: Value = serde_json::from_slice(V2_REPORT_JSON).unwrap();
+        edit(&mut json);
+        serde_json::to_vec(&json).unwrap()
+    }
+
+    /// The fixture recast as a version 3 report from a Genoa CPU
+    /// (family 0x19, model 0x11, stepping 1).
+    fn v3_report_json() -> Vec<u8> {
+        edited_json(|json| {
+            json["version"] = 3.into();
+            json["_reserved_1"][0] = 0x19.into();
+            json["_reserved_1"][1] = 0x11.into();
+            json["_reserved_1"][2] = 0x01.into();
+        })
+    }
+
+    #[test]
+    fn parses_v2_report_without_cpuid() {
+        let report = parse_report(V2_REPORT_JSON).unwrap();
+        assert_eq!(report_version(&report), 2);
+        assert_eq!(report_cpuid(&report), None);
+        assert_eq!(report.current_tcb.snp, 22);
+        assert_eq!(report.current_tcb.microcode, 213);
+        assert_eq!(current_build(&report), 20);
+    }
+
+    #[test]
+    fn parses_v3_report_with_cpuid() {
+        let report = parse_report(&v3_report_json()).unwrap();
+        assert_eq!(report_version(&report), 3);
+        assert_eq!(
+            report_cpuid(&report),
+            Some(CpuidInfo {
+                family_id: 0x19,
+                model_id: 0x11,
+                stepping: 0x01,
+            })
+        );
+        assert!(check_reserved_fields(&report).is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_report_versions() {
+        for version in [0, 1, 4] {
+            let json = edited_json(|json| json["version"] = version.into());
+            assert!(matches!(
+                parse_report(&json),
+                Err(ReportParseError::UnsupportedVersion { version: v }) if v == version
+            ));
+        }
+    }
+
+    /// The fixture report in the binary layout the firmware returns.
+    fn v2_report_bytes() -> Vec<u8> {
+        bincode::serialize(&parse_report(V2_REPORT_JSON).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn parses_binary_report() {
+        let bytes = v2_report_bytes();
+        assert_eq!(bytes.len(), REPORT_LEN);
+        let report = parse_report_bytes(&bytes).unwrap();
+        assert_eq!(bincode::serialize(&report).unwrap(), bytes);
+    }
+
+    #[test]
+    fn rejects_binary_report_of_wrong_length() {
+        let bytes = v2_report_bytes();
+        for len in [0, REPORT_LEN - 1, REPORT_LEN + 1] {
+            let mut input = bytes.clone();
+            input.resize(len, 0);
+            assert!(matches!(
+                parse_report_bytes(&input),
+                Err(ReportParseError::WrongLength { expected: REPORT_LEN, actual }) if actual == len
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_zeroed_binary_report_version() {
+        let mut bytes = v2_report_bytes();
+        bytes[..4].fill(0);
+        assert!(matches!(
+            parse_report_bytes(&bytes),
+            Err(ReportParseError::UnsupportedVersion { version: 0 })
+        ));
+    }
+
+    #[test]
+    fn rejects_binary_report_with_other_signature_algorithm() {
+        let mut bytes = v2_report_bytes();
+        bytes[SIG_ALGO_OFFSET..SIG_ALGO_OFFSET + 4].copy_from_slice(&2u32.to_le_bytes());
+        assert!(matches!(
+            parse_report_bytes(&bytes),
+            Err(ReportParseError::UnsupportedSignatureAlgorithm { algo: 2 })
+        ));
+    }
+
+    #[test]
+    fn rejects_binary_report_with_reserved_bits_set() {
+        let mut report = parse_report(V2_REPORT_JSON).unwrap();
+        report._reserved_3 = 1;
+        let bytes = bincode::serialize(&report).unwrap();
+        assert!(matches!(
+            parse_report_bytes(&bytes),
+            Err(ReportParseError::ReservedNonZero { field: "_reserved_3" })
+        ));
+    }
+
+    #[test]
+    fn takes_product_line_from_v3_cpuid() {
+        let report = parse_report(&v3_report_json()).unwrap();
+        assert_eq!(report_product_line(&report, None), Ok(ProductLine::Genoa));
+        assert_eq!(
+            report_product_line(&report, Some(ProductLine::Milan)),
+            Ok(ProductLine::Genoa)
+        );
+    }
+
+    #[test]
+    fn requires_product_line_for_v2_reports() {
+        let report = parse_report(V2_REPORT_JSON).unwrap();
+        assert_eq!(
+            report_product_line(&report, Some(ProductLine::Milan)),
+            Ok(ProductLine::Milan)
+        );
+        assert!(report_product_line(&report, None).is_err());
+    }
+
+    #[test]
+    fn maps_cpuid_to_product_line() {
+        let cases = [
+            (0x19, 0x01, Some(ProductLine::Milan)),
+            (0x19, 0x11, Some(ProductLine::Genoa)),
+            (0x19, 0xa0, Some(ProductLine::Genoa)),
+            (0x1a, 0x02, Some(ProductLine::Turin)),
+            (0x1a, 0x11, Some(ProductLine::Turin)),
+            (0x1a, 0x44, None),
+            (0x17, 0x31, None),
+        ];
+        for (family_id, model_id, expected) in cases {
+            let cpuid = CpuidInfo { family_id, model_id, stepping: 0 };
+            assert_eq!(cpuid.product_line(), expected, "{family_id:#x}/{model_id:#x}");
+        }
+    }
+
+    #[test]
+    fn takes_turin_product_line_from_v3_cpuid() {
+        let json = edited_json(|json| {
+            json["version"] = 3.into();
+            json["_reserved_1"][0] = 0x1a.into();
+            json["_reserved_1"][1] = 0x02.into();
+        });
+        let report = parse_report(&json).unwrap();
+        assert_eq!(report_product_line(&report, Some(ProductLine::Milan)), Ok(ProductLine::Turin));
+    }
+
+    /// The fixture report wrapped in a `MSG_REPORT_RSP` as read back from
+    /// `/dev/sev-guest`.
+    fn guest_response(status: u32, report_size: u32) -> Vec<u8> {
+        let mut raw = vec![0u8; GUEST_RESPONSE_HEADER_LEN];
+        raw[0..4].copy_from_slice(&status.to_le_bytes());
+        raw[4..8].copy_from_slice(&report_size.to_le_bytes());
+        raw.extend(v2_report_bytes());
+        raw
+    }
+
+    #[test]
+    fn parses_report_from_guest_response() {
+        let report = parse_guest_response(&guest_response(0, REPORT_LEN as u32)).unwrap();
+        assert_eq!(bincode::serialize(&report).unwrap(), v2_report_bytes());
+    }
+
+    #[test]
+    fn rejects_guest_response_with_firmware_error() {
+        assert!(matches!(
+            parse_guest_response(&guest_response(0x16, REPORT_LEN as u32)),
+            Err(ReportParseError::FirmwareStatus { status: 0x16 })
+        ));
+    }
+
+    #[test]
+    fn rejects_guest_response_with_wrong_report_size() {
+        assert!(matches!(
+            parse_guest_response(&guest_response(0, 0x2a0)),
+            Err(ReportParseError::WrongLength { expected: REPORT_LEN, actual: 0x2a0 })
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_guest_response() {
+        let raw = guest_response(0, REPORT_LEN as u32);
+        assert!(matches!(
+            parse_guest_response(&raw[..raw.len() - 1]),
+            Err(ReportParseError::WrongLength { .. })
+        ));
+        assert!(parse_guest_response(&raw[..GUEST_RESPONSE_HEADER_LEN - 1]).is_err());
+    }
+
+    #[test]
+    fn quick_rejects_malformed_input() {
+        let bytes = v2_report_bytes();
+        assert!(quick_reject(&bytes).is_none());
+        assert!(matches!(
+            quick_reject(&bytes[..REPORT_LEN - 1]),
+            Some(ReportParseError::WrongLength { .. })
+        ));
+        let mut future = bytes.clone();
+        future[..4].copy_from_slice(&9u32.to_le_bytes());
+        assert!(matches!(
+            quick_reject(&future),
+            Some(ReportParseError::UnsupportedVersion { version: 9 })
+        ));
+    }
+
+    #[cfg(feature = "pure-rust")]
+    #[test]
+    fn converts_signature_for_pure_rust_verifiers() {
+        use openssl::bn::BigNumContext;
+        use openssl::ec::PointConversionForm;
+        use p384::ecdsa::signature::Verifier;
+
+        let (chain, report) = crate::test_util::generate_test_chain().unwrap();
+        let ec_key = chain.vek.public_key().unwrap().ec_key().unwrap();
+        let point = ec_key
+            .public_key()
+            .to_bytes(ec_key.group(), PointConversionForm::UNCOMPRESSED, &mut BigNumContext::new().unwrap())
+            .unwrap();
+        let verifying_key = p384::ecdsa::VerifyingKey::from_sec1_bytes(&point).unwrap();
+
+        let signature = ecdsa_signature(&report).unwrap();
+        verifying_key.verify(&signed_bytes(&report).unwrap(), &signature).unwrap();
+
+        let mut tampered = report;
+        tampered.guest_svn ^= 1;
+        assert!(verifying_key.verify(&signed_bytes(&tampered).unwrap(), &signature).is_err());
+    }
+
+    #[cfg(feature = "pure-rust")]
+    #[test]
+    fn rejects_oversized_signature_component() {
+        let mut report = parse_report(V2_REPORT_JSON).unwrap();
+        report.signature.r[48] = 1;
+        assert!(ecdsa_signature(&report).is_err());
+    }
+
+    #[test]
+    fn strict_reserved_accepts_clean_report() {
+        let policy = ReportPolicy { strict_reserved: true, ..Default::default() };
+        assert!(policy.check(&parse_report(V2_REPORT_JSON).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn strict_reserved_rejects_dirtied_json_reserved_regions() {
+        let policy = ReportPolicy { strict_reserved: true, ..Default::default() };
+        let dirty_reserved_4 = edited_json(|json| json["_reserved_4"][167] = 1.into());
+        let dirty_signature = edited_json(|json| json["signature"]["_reserved"][0] = 1.into());
+        for (json, field) in [(dirty_reserved_4, "_reserved_4"), (dirty_signature, "signature._reserved")] {
+            let report = parse_report(&json).unwrap();
+            let err = policy.check(&report).unwrap_err();
+            assert!(err.contains(field), "{err}");
+            assert!(ReportPolicy::default().check(&report).is_ok());
+        }
+    }
+
+    #[test]
+    fn decodes_all_four_tcb_fields() {
+        let report = parse_report(V2_REPORT_JSON).unwrap();
+        for tcb in [current_tcb(&report), committed_tcb(&report), reported_tcb(&report), launch_tcb(&report)] {
+            assert_eq!((tcb.bootloader, tcb.tee, tcb.snp, tcb.microcode), (4, 0, 22, 213));
+        }
+
+        // Give each field distinct components at its offset in the binary layout.
+        let mut raw = v2_report_bytes();
+        for (offset, svn) in [(0x38, 1), (0x180, 2), (0x1e0, 3), (0x1f0, 4)] {
+            raw[offset..offset + 8].copy_from_slice(&[svn, svn + 10, 0, 0, 0, 0, svn + 20, svn + 30]);
+        }
+        let report = parse_report_bytes(&raw).unwrap();
+        let components = |tcb: TcbVersion| (tcb.bootloader, tcb.tee, tcb.snp, tcb.microcode);
+        assert_eq!(components(current_tcb(&report)), (1, 11, 21, 31));
+        assert_eq!(components(reported_tcb(&report)), (2, 12, 22, 32));
+        assert_eq!(components(committed_tcb(&report)), (3, 13, 23, 33));
+        assert_eq!(components(launch_tcb(&report)), (4, 14, 24, 34));
+    }
+
+    #[test]
+    fn round_trips_tcb_encodings() {
+        let tcb = tcb_from_le_bytes([3, 0, 0, 0, 0, 0, 8, 115]);
+        assert_eq!((tcb.bootloader, tcb.tee, tcb.snp, tcb.microcode), (3, 0, 8, 115));
+        assert_eq!(tcb_to_u64(&tcb), 0x7308_0000_0000_0003);
+        assert_eq!(tcb_to_le_bytes(&tcb_from_u64(0x7308_0000_0000_0003)), [3, 0, 0, 0, 0, 0, 8, 115]);
+    }
+
+    #[test]
+    fn decodes_platform_info_flags() {
+        let report = parse_report(V2_REPORT_JSON).unwrap();
+        assert_eq!(platform_info(&report), PlatformInfoFlags { smt_enabled: true, tsme_enabled: true });
+
+        let report = parse_report(&edited_json(|json| json["plat_info"] = 2.into())).unwrap();
+        assert_eq!(platform_info(&report), PlatformInfoFlags { smt_enabled: false, tsme_enabled: true });
+    }
+
+    #[test]
+    fn policy_enforces_platform_info() {
+        let report = parse_report(V2_REPORT_JSON).unwrap();
+        let forbid_smt = ReportPolicy { forbid_smt: true, ..Default::default() };
+        assert!(forbid_smt.check(&report).unwrap_err().contains("SMT"));
+
+        let report = parse_report(&edited_json(|json| json["plat_info"] = 1.into())).unwrap();
+        let require_tsme = ReportPolicy { require_tsme: true, ..Default::default() };
+        assert!(require_tsme.check(&report).unwrap_err().contains("TSME"));
+        assert!(forbid_smt.check(&parse_report(&edited_json(|json| json["plat_info"] = 2.into())).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn decodes_guest_policy() {
+        let report = parse_report(V2_REPORT_JSON).unwrap();
+        assert_eq!(
+            guest_policy(&report),
+            GuestPolicyFlags { smt_allowed: true, ..Default::default() }
+        );
+
+        // ABI 1.51, SMT, migration agent and debug allowed, single socket.
+        let raw: u64 = 0x33 | (1 << 8) | (0b11101 << 16);
+        let report = parse_report(&edited_json(|json| json["policy"] = raw.into())).unwrap();
+        assert_eq!(
+            guest_policy(&report),
+            GuestPolicyFlags {
+                abi_minor: 51,
+                abi_major: 1,
+                smt_allowed: true,
+                migrate_ma_allowed: true,
+                debug_allowed: true,
+                single_socket_required: true,
+            }
+        );
+    }
+
+    #[test]
+    fn policy_matches_raw_guest_policy_exactly() {
+        let report = parse_report(V2_REPORT_JSON).unwrap();
+        assert_eq!(policy_raw(&report), 0x30000);
+        let exact = ReportPolicy { expected_policy: Some(0x30000), ..Default::default() };
+        assert!(exact.check(&report).is_ok());
+
+        // Only the debug bit differs.
+        let debug = parse_report(&edited_json(|json| json["policy"] = 0xb0000.into())).unwrap();
+        assert_eq!(policy_raw(&debug), 0xb0000);
+        assert_eq!(
+            exact.check(&debug).unwrap_err(),
+            "Guest policy 0x00000000000b0000 does not match the expected policy 0x0000000000030000"
+        );
+    }
+
+    #[test]
+    fn pins_fixture_report_ids() {
+        let report = parse_report(V2_REPORT_JSON).unwrap();
+        assert_eq!(
+            hex::encode(report_id(&report)),
+            "af62e020f8e0515331ee2735ddeb39744b8cdefeff108a080b02821fc3975665"
+        );
+        // The fixture guest was launched without a migration agent.
+        assert_eq!(report_id_ma(&report), [0xff; 32]);
+    }
+
+    #[test]
+    fn policy_rejects_seen_report_id() {
+        let report = parse_report(V2_REPORT_JSON).unwrap();
+        let mut policy = ReportPolicy::default();
+        policy.seen_report_ids.insert([0; 32]);
+        assert!(policy.check(&report).is_ok());
+
+        policy.seen_report_ids.insert(report_id(&report));
+        assert_eq!(
+            policy.check(&report).unwrap_err(),
+            format!("Report ID {} has already been seen", hex::encode(report_id(&report)))
+        );
+    }
+
+    #[test]
+    fn rejects_guest_policy_below_minimum_abi() {
+        let raw: u64 = 0x33 | (1 << 8) | (0b111 << 16);
+        let report = parse_report(&edited_json(|json| json["policy"] = raw.into())).unwrap();
+        let policy = |min_abi| ReportPolicy { min_abi: Some(min_abi), ..Default::default() };
+        assert!(policy((1, 51)).check(&report).is_ok());
+        assert!(policy((1, 52)).check(&report).unwrap_err().contains("below the required minimum"));
+        assert!(policy((2, 0)).check(&report).is_err());
+
+        let forbid_ma = ReportPolicy { forbid_migration_agent: true, ..Default::default() };
+        assert!(forbid_ma.check(&report).unwrap_err().contains("migration agent"));
+    }
+
+    #[test]
+    fn matches_family_and_image_ids() {
+        let family: Vec<u8> = (1..=16).collect();
+        let image: Vec<u8> = (17..=32).collect();
+        let report = parse_report(&edited_json(|json| {
+            json["family_id"] = family.clone().into();
+            json["image_id"] = image.clone().into();
+        }))
+        .unwrap();
+        assert_eq!(family_id(&report).to_vec(), family);
+        assert_eq!(image_id(&report).to_vec(), image);
+
+        let policy = ReportPolicy {
+            family_id: Some(family_id(&report)),
+            image_id: Some(image_id(&report)),
+            ..Default::default()
+        };
+        assert!(policy.check(&report).is_ok());
+
+        // The fixture was launched without an ID block, so both IDs are zero.
+        let fixture = parse_report(V2_REPORT_JSON).unwrap();
+        assert_eq!(family_id(&fixture), [0; 16]);
+        assert_eq!(policy.violations(&fixture).len(), 2);
+        assert!(policy.check(&fixture).unwrap_err().contains("family ID"));
+    }
+
+    #[test]
+    fn exports_decoded_fields_as_json() {
+        let report = parse_report(V2_REPORT_JSON).unwrap();
+        let json = report_to_json(&report);
+        assert_eq!(json["version"], 2);
+        assert_eq!(json["vmpl"], 1);
+        assert_eq!(json["policy"]["raw"], 0x30000);
+        assert_eq!(json["policy"]["smt_allowed"], true);
+        assert_eq!(json["plat_info"]["tsme_enabled"], true);
+        assert_eq!(json["measurement"], hex::encode(report.measurement));
+        assert_eq!(json["chip_id"].as_str().unwrap().len(), 128);
+        assert_eq!(json["reported_tcb"]["snp"], 22);
+        assert_eq!(json["reported_tcb"]["microcode"], 213);
+        assert_eq!(json["reported_tcb"]["raw"], tcb_to_u64(&report.reported_tcb));
+        assert_eq!(json["current_version"], "1.55.20");
+        assert!(json["cpuid"].is_null());
+        assert!(json.get("_reserved_4").is_none());
+
+        let v3 = report_to_json(&parse_report(&v3_report_json()).unwrap());
+        assert_eq!(v3["cpuid"]["family_id"], 0x19);
+    }
+
+    #[test]
+    fn signed_region_is_672_bytes() {
+        let report = parse_report(V2_REPORT_JSON).unwrap();
+        let signed = signed_bytes(&report).unwrap();
+        assert_eq!(signed.len(), 672);
+        assert_eq!(signed, v2_report_bytes()[..0x2A0]);
+
+        let (r, s) = signature_raw(&report);
+        assert_eq!((r, s), (&report.signature.r[..], &report.signature.s[..]));
+    }
+
+    #[test]
+    fn verifies_v2_and_v3_reports_over_their_signed_regions() {
+        assert_eq!(signed_region_len(2).unwrap(), 0x2A0);
+        assert_eq!(signed_region_len(3).unwrap(), 0x2A0);
+        for version in [1, 4] {
+            assert!(matches!(
+                signed_region_len(version),
+                Err(ReportParseError::UnsupportedVersion { version: v }) if v == version
+            ));
+        }
+
+        let key = generate_key().unwrap();
+        let public = PKey::public_key_from_der(&key.public_key_to_der().unwrap()).unwrap();
+        for json in [V2_REPORT_JSON.to_vec(), v3_report_json()] {
+            let mut report = parse_report(&json).unwrap();
+            sign_report(&mut report, &key).unwrap();
+            assert!(verify_report_with_key(&public, &report).is_ok(), "v{}", report.version);
+
+            // The CPUID bytes a v3 report assigns lie inside the signed region.
+            let mut tampered = report;
+            tampered._reserved_1[0] ^= 1;
+            assert!(verify_report_with_key(&public, &tampered).is_err(), "v{}", report.version);
+        }
+
+        let mut unsupported = parse_report(V2_REPORT_JSON).unwrap();
+        unsupported.version = 4;
+        assert!(signed_bytes(&unsupported).is_err());
+    }
+
+    #[test]
+    fn verifies_report_against_pinned_vcek_key() {
+        let (chain, report) = generate_test_chain().unwrap();
+        let key = chain.vek.public_key().unwrap();
+        assert!(verify_report_with_key(&key, &report).is_ok());
+
+        let (other, _) = generate_test_chain().unwrap();
+        let err = verify_report_with_key(&other.vek.public_key().unwrap(), &report).unwrap_err();
+        assert!(err.contains("does not match"));
+    }
+
+    #[test]
+    fn decodes_vcek_and_vlek_signing_keys() {
+        let vcek_signed = parse_report(V2_REPORT_JSON).unwrap();
+        assert_eq!(signing_key(&vcek_signed), Ok(SigningKeyKind::Vcek));
+
+        let vlek_signed = parse_report(&edited_json(|json| json["_author_key_en"] = (1 << 2).into())).unwrap();
+        assert_eq!(signing_key(&vlek_signed), Ok(SigningKeyKind::Vlek));
+
+        let reserved = parse_report(&edited_json(|json| json["_author_key_en"] = (3 << 2).into())).unwrap();
+        assert!(signing_key(&reserved).is_err());
+    }
+
+    #[test]
+    fn binds_session_key_into_report_data() {
+        let key = generate_key().unwrap().public_key_to_der().unwrap();
+        let other = generate_key().unwrap().public_key_to_der().unwrap();
+        let mut report = parse_report(V2_REPORT_JSON).unwrap();
+        report.report_data = bind_key_report_data(&key);
+
+        assert_eq!(report.report_data[..], openssl::sha::sha512(&key)[..]);
+        assert_eq!(verify_bound_key(&report, &key), Ok(true));
+        assert_eq!(verify_bound_key(&report, &other), Ok(false));
+        assert!(verify_bound_key(&report, &[]).is_err());
+    }
+
+    #[test]
+    fn policy_enforces_required_vmpl() {
+        let vmpl0_only = ReportPolicy { required_vmpl: Some(0), ..Default::default() };
+        let vmpl0 = parse_report(&edited_json(|json| json["vmpl"] = 0.into())).unwrap();
+        assert_eq!(vmpl(&vmpl0), 0);
+        assert!(vmpl0_only.check(&vmpl0).is_ok());
+
+        let vmpl2 = parse_report(&edited_json(|json| json["vmpl"] = 2.into())).unwrap();
+        assert_eq!(vmpl(&vmpl2), 2);
+        assert!(vmpl0_only.check(&vmpl2).unwrap_err().contains("VMPL2"));
+    }
+
+    #[test]
+    fn policy_enforces_min_guest_svn() {
+        let min_svn_5 = ReportPolicy { min_guest_svn: Some(5), ..Default::default() };
+        let above = parse_report(&edited_json(|json| json["guest_svn"] = 7.into())).unwrap();
+        assert_eq!(guest_svn(&above), 7);
+        assert!(min_svn_5.check(&above).is_ok());
+
+        let at_min = parse_report(&edited_json(|json| json["guest_svn"] = 5.into())).unwrap();
+        assert!(min_svn_5.check(&at_min).is_ok());
+
+        let below = parse_report(&edited_json(|json| json["guest_svn"] = 4.into())).unwrap();
+        assert_eq!(guest_svn(&below), 4);
+        let err = min_svn_5.check(&below).unwrap_err();
+        assert!(err.contains("Guest SVN 4 is below the required minimum 5"), "{err}");
+    }
+
+    #[test]
+    fn policy_detects_firmware_rollback() {
+        let forbid_rollback = ReportPolicy { forbid_rollback: true, ..Default::default() };
+        assert!(forbid_rollback.check(&parse_report(V2_REPORT_JSON).unwrap()).is_ok());
+
+        let rolled_back = parse_report(&edited_json(|json| json["committed_tcb"]["snp"] = 21.into())).unwrap();
+        assert!(forbid_rollback.check(&rolled_back).unwrap_err().contains("rollback"));
+        assert!(ReportPolicy::default().check(&rolled_back).is_ok());
+    }
+
+    #[test]
+    fn verifies_raw_and_der_signatures_alike() {
+        let (chain, report) = generate_test_chain().unwrap();
+        let key = chain.vek.public_key().unwrap();
+        let signed = signed_bytes(&report).unwrap();
+        let mut raw = [0u8; 144];
+        raw[..72].copy_from_slice(&report.signature.r);
+        raw[72..].copy_from_slice(&report.signature.s);
+        let raw = SignatureInput::RawRS(raw);
+        let der = SignatureInput::Der(raw.to_ecdsa_sig().unwrap().to_der().unwrap());
+
+        assert!(verify_report_with_signature(&key, &signed, &raw).is_ok());
+        assert!(verify_report_with_signature(&key, &signed, &der).is_ok());
+        assert!(verify_report_with_signature(&key, &signed[1..], &der).is_err());
+        assert!(verify_report_with_signature(&key, &signed, &SignatureInput::Der(vec![0x30, 0x00])).is_err());
+    }
+
+    #[test]
+    fn policy_matches_host_data() {
+        let bound: [u8; 32] = std::array::from_fn(|i| i as u8);
+        let report = parse_report(&edited_json(|json| json["host_data"] = bound.to_vec().into())).unwrap();
+        assert_eq!(host_data(&report), bound);
+
+        let policy = ReportPolicy { host_data: Some(bound), ..Default::default() };
+        assert!(policy.check(&report).is_ok());
+        let fixture = parse_report(V2_REPORT_JSON).unwrap();
+        assert_eq!(host_data(&fixture), [0; 32]);
+        assert!(policy.check(&fixture).unwrap_err().contains("host data"));
+    }
+
+    #[test]
+    fn correlates_reports_by_chip() {
+        let report = parse_report(V2_REPORT_JSON).unwrap();
+        let mut other_chip = report;
+        other_chip.chip_id[63] ^= 0x01;
+
+        assert!(same_chip(&[]));
+        assert!(same_chip(&[report, report, report]));
+        assert!(!same_chip(&[report, report, other_chip]));
+        assert!(!same_chip(&[other_chip, report]));
+    }
+
+    #[test]
+    fn round_trips_hwid_through_kds_hex() {
+        let report = parse_report(V2_REPORT_JSON).unwrap();
+        let hex_str = hwid_to_kds_hex(&report.chip_id);
+        assert_eq!(hex_str.len(), 128);
+        assert_eq!(hex_str, hex_str.to_ascii_lowercase());
+        assert_eq!(kds_hex_to_hwid(&hex_str).unwrap(), report.chip_id);
+
+        let hwid: [u8; 64] = std::array::from_fn(|i| (i * 4) as u8);
+        assert_eq!(kds_hex_to_hwid(&hwid_to_kds_hex(&hwid)).unwrap(), hwid);
+    }
+
+    #[test]
+    fn rejects_kds_hex_of_wrong_length_or_case() {
+        let hex_str = hwid_to_kds_hex(&[0xab; 64]);
+        for len in [0, 126, 127] {
+            let err = kds_hex_to_hwid(&hex_str[..len]).unwrap_err();
+            assert_eq!(err, format!("KDS HWID must be 128 hex digits, got {len}"));
+        }
+        assert!(kds_hex_to_hwid(&format!("{hex_str}ab")).is_err());
+        let err = kds_hex_to_hwid(&hex_str.to_ascii_uppercase()).unwrap_err();
+        assert_eq!(err, "KDS HWID must contain only lowercase hex digits");
+    }
+
+    #[test]
+    fn round_trips_kds_query_string() {
+        let tcb = tcb_from_le_bytes([3, 0, 0, 0, 0, 0, 8, 115]);
+        let query = tcb_to_kds_query(&tcb);
+        assert_eq!(query, "blSPL=03&teeSPL=00&snpSPL=08&ucodeSPL=115");
+
+        let parsed = tcb_from_kds_query(&format!("?{query}")).unwrap();
+        assert_eq!(tcb_to_u64(&parsed), tcb_to_u64(&tcb));
+        assert!(tcb_from_kds_query("blSPL=03&teeSPL=00&snpSPL=08").is_err());
+        assert!(tcb_from_kds_query("blSPL=03&blSPL=03&teeSPL=00&snpSPL=08&ucodeSPL=115").is_err());
+        assert!(tcb_from_kds_query("blSPL=256&teeSPL=00&snpSPL=08&ucodeSPL=115").is_err());
+    }
+
+    #[test]
+    fn policy_rejects_century_long_certificate() {
+        const YEAR: u64 = 365 * 24 * 60 * 60;
+        let policy = ReportPolicy {
+            max_validity: Some(Duration::from_secs(30 * YEAR)),
+            ..Default::default()
+        };
+        let (mut chain, _) = generate_test_chain().unwrap();
+        assert!(policy.check_chain(&chain).is_ok());
+
+        let key = generate_key().unwrap();
+        let extensions = vec![
+            BasicConstraints::new().critical().ca().build().unwrap(),
+            KeyUsage::new().critical().key_cert_sign().crl_sign().build().unwrap(),
+        ];
+        chain.ca.ark = mint_valid_for("ARK-Test", &key, "ARK-Test", &key, 1, extensions, 100 * 365).unwrap();
+        let err = policy.check_chain(&chain).unwrap_err();
+        assert!(err.starts_with("ARK is valid for"), "{err}");
+    }
+
+    #[test]
+    fn verifies_tls_channel_binding() {
+        let exporter = [0x5a; 32];
+        let mut report = parse_report(V2_REPORT_JSON).unwrap();
+        report.report_data = bind_channel_report_data(&exporter);
+
+        assert_eq!(verify_channel_binding(&report, &exporter), Ok(true));
+        assert_eq!(verify_channel_binding(&report, &[0xa5; 32]), Ok(false));
+        assert!(verify_channel_binding(&report, &[]).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! AMD root keys embedded at build time, for verifying chains without
+//! contacting KDS (e.g., in air-gapped deployments).
+
+use sev::certs::snp::{Certificate, Chain};
+use crate::certs::from_pem;
+use crate::product::ProductLine;
+use crate::trust::TrustStore;
+pub use crate::trust::verify_with_trusted_roots;
+
+/// Returns the PEM of the AMD Root Key bundled for the given product line,
+/// if one is bundled.
+fn bundled_pem(product: ProductLine) -> Option<&'static [u8]> {
+    match product {
+        ProductLine::Milan => Some(sev::certs::snp::builtin::milan::ARK),
+        ProductLine::Genoa => Some(sev::certs::snp::builtin::genoa::ARK),
+        // Neither the sev crate nor this repository ships AMD's Turin ARK
+        // yet, so Turin chains must be verified against caller-supplied
+        // roots (see `trust::TrustStore`) until it is added here.
+        ProductLine::Turin => None,
+    }
+}
+
+/// Returns the AMD Root Key bundled for the given product line.
+///
+/// # Errors
+/// Returns an error if no root is bundled for the product line or it fails to parse.
+pub fn bundled_root(product: ProductLine) -> Result<Certificate, Box<dyn std::error::Error>> {
+    let pem = bundled_pem(product).ok_or_else(|| format!("No ARK is bundled for {product}"))?;
+    from_pem(pem)
+}
+
+/// Returns every bundled AMD Root Key.
+///
+/// # Errors
+/// Returns an error if a bundled root fails to parse.
+pub fn bundled_roots() -> Result<Vec<Certificate>, Box<dyn std::error::Error>> {
+    ProductLine::ALL
+        .into_iter()
+        .filter_map(bundled_pem)
+        .map(from_pem)
+        .collect()
+}
+
+/// The roots bundled with the crate, for every product line.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BundledRoots;
+
+impl TrustStore for BundledRoots {
+    fn roots(&self) -> Result<Vec<Certificate>, Box<dyn std::error::Error>> {
+        bundled_roots()
+    }
+}
+
+/// Verifies a chain against the bundled root for its product line, without network access.
+///
+/// # Errors
+/// Returns an error if no root is bundled for the product line, the ARK
+/// does not match it, or the chain does not verify.
+pub fn verify_with_bundled_root(
+    chain: &Chain,
+    product: ProductLine,
+) -> Result<(), Box<dyn std::error::Error>> {
+    verify_with_trusted_roots(chain, &vec![bundled_root(product)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certs::{common_name, eq_der};
+    use crate::test_util::generate_test_chain;
+    use sev::certs::snp::Verifiable;
+
+    /// The Milan ASK and ARK as served by KDS.
+    const MILAN_CERT_CHAIN: &[u8] = include_bytes!("../../../certificates/amd-vcek-v1-Milan-cert_chain.pem");
+
+    #[test]
+    fn bundled_roots_parse_and_self_verify() {
+        for product in ProductLine::ALL.into_iter().filter(|&p| bundled_pem(p).is_some()) {
+            let root = bundled_root(product).unwrap();
+            (&root, &root).verify().unwrap();
+            let name = common_name(&root).unwrap();
+            assert_eq!(ProductLine::from_cn_suffix(&name), Some(product), "{name}");
+        }
+    }
+
+    #[test]
+    fn bundled_milan_root_matches_kds_chain() {
+        let ca = crate::certs::ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        assert!(eq_der(&ca.ark, &bundled_root(ProductLine::Milan).unwrap()).unwrap());
+        (&ca.ark, &ca.ask).verify().unwrap();
+    }
+
+    #[test]
+    fn empty_trust_store_falls_back_to_bundled_roots() {
+        // A chain under the bundled Milan ARK gets past the root check and
+        // only fails on the foreign VCEK's signature.
+        let (test_chain, _) = generate_test_chain().unwrap();
+        let ca = crate::certs::ca_chain_from_kds_response(MILAN_CERT_CHAIN).unwrap();
+        let chain = Chain { ca, vek: test_chain.vek };
+        let err = verify_with_trusted_roots(&chain, &Vec::new()).unwrap_err();
+        assert!(!err.to_string().contains("trusted root"), "{err}");
+
+        // A self-consistent chain whose ARK is not bundled is still refused.
+        let (own_root_chain, _) = generate_test_chain().unwrap();
+        let err = verify_with_trusted_roots(&own_root_chain, &Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("trusted root"), "{err}");
+    }
+
+    #[test]
+    fn names_product_line_without_bundled_root() {
+        assert_eq!(bundled_roots().unwrap().len(), 2);
+        let err = bundled_root(ProductLine::Turin).unwrap_err();
+        assert_eq!(err.to_string(), "No ARK is bundled for Turin");
+    }
+}
@@ -0,0 +1,91 @@
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// A byte buffer holding secret-like data that is wiped when dropped.
+///
+/// Use this for intermediate buffers in verification and parsing paths that
+/// should not linger in freed memory (e.g., future private key material).
+#[derive(Clone, Default, Zeroize, ZeroizeOnDrop)]
+pub struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+    /// Wraps an existing buffer, taking ownership of it.
+    pub fn new(bytes: Vec<u8>) -> Self {
+        SecretBytes(bytes)
+    }
+
+    /// Returns the wrapped bytes.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns the number of wrapped bytes.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Reports whether the buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Copies the bytes into a fixed-size array that is also wiped when
+    /// dropped, for APIs that take secrets by value.
+    ///
+    /// Returns `None` if the buffer is not exactly `N` bytes long.
+    pub fn to_array<const N: usize>(&self) -> Option<Zeroizing<[u8; N]>> {
+        if self.0.len() != N {
+            return None;
+        }
+        let mut array = Zeroizing::new([0u8; N]);
+        array.copy_from_slice(&self.0);
+        Some(array)
+    }
+}
+
+impl From<&[u8]> for SecretBytes {
+    fn from(bytes: &[u8]) -> Self {
+        SecretBytes(bytes.to_vec())
+    }
+}
+
+impl std::fmt::Debug for SecretBytes {
+    // Never print the contents.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecretBytes({} bytes)", self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compiles only if `T` wipes itself when dropped.
+    fn assert_zeroize_on_drop<T: ZeroizeOnDrop>() {}
+
+    #[test]
+    fn wipes_buffer_in_place() {
+        assert_zeroize_on_drop::<SecretBytes>();
+
+        let mut secret = SecretBytes::new(vec![0xa5; 64]);
+        let ptr = secret.as_slice().as_ptr();
+        secret.zeroize();
+        // Zeroizing a vector clears it but keeps its allocation, so the
+        // original bytes can still be inspected until the wrapper is dropped.
+        let wiped = unsafe { std::slice::from_raw_parts(ptr, 64) };
+        assert!(wiped.iter().all(|&b| b == 0));
+        assert!(secret.is_empty());
+    }
+
+    #[test]
+    fn copies_into_array_of_matching_length_only() {
+        let secret = SecretBytes::from(&[7u8; 64][..]);
+        assert_eq!(*secret.to_array::<64>().unwrap(), [7u8; 64]);
+        assert!(secret.to_array::<32>().is_none());
+    }
+
+    #[test]
+    fn debug_output_hides_contents() {
+        let secret = SecretBytes::new(vec![0x42; 4]);
+        assert_eq!(format!("{secret:?}"), "SecretBytes(4 bytes)");
+    }
+}
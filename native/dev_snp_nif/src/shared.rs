@@ -0,0 +1,175 @@
+//! A `Send + Sync` form of a verified chain, for storing in long-lived state
+//! shared across threads (e.g., a rustler resource held by many processes).
+//!
+//! `Certificate` wraps an OpenSSL `X509`, which the `openssl` crate marks
+//! `Send + Sync` because OpenSSL reference-counts it atomically. `SharedChain`
+//! holds plain DER bytes instead, so sharing it does not depend on that
+//! guarantee holding for every OpenSSL build, and cloning it is cheap to reason about.
+
+use sev::certs::snp::{ca, Certificate, Chain};
+
+/// Magic bytes opening the compact binary encoding of a chain.
+const MAGIC: &[u8; 4] = b"SNPC";
+/// Version of the compact binary encoding.
+const FORMAT_VERSION: u8 = 1;
+
+/// The DER encodings of a chain's ARK, ASK and VCEK.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedChain {
+    /// DER encoding of the ARK.
+    pub ark: Vec<u8>,
+    /// DER encoding of the ASK.
+    pub ask: Vec<u8>,
+    /// DER encoding of the VCEK (or VLEK).
+    pub vek: Vec<u8>,
+}
+
+impl SharedChain {
+    /// Encodes each certificate of the chain as DER.
+    ///
+    /// # Errors
+    /// Returns an error if a certificate cannot be encoded.
+    pub fn from_chain(chain: &Chain) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(SharedChain {
+            ark: chain.ca.ark.to_der()?,
+            ask: chain.ca.ask.to_der()?,
+            vek: chain.vek.to_der()?,
+        })
+    }
+
+    /// Decodes the certificates back into a `Chain`.
+    ///
+    /// # Errors
+    /// Returns an error if a stored encoding no longer parses.
+    pub fn to_chain(&self) -> Result<Chain, Box<dyn std::error::Error>> {
+        Ok(Chain {
+            ca: ca::Chain::from_der(&self.ark, &self.ask)?,
+            vek: Certificate::from_der(&self.vek)?,
+        })
+    }
+
+    /// Encodes the chain compactly for caching: `MAGIC`, the format version
+    /// byte, then the ARK, ASK and VCEK DER each prefixed with its length as
+    /// a big-endian `u32`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(MAGIC.len() + 1 + 12 + self.ark.len() + self.ask.len() + self.vek.len());
+        out.extend_from_slice(MAGIC);
+        out.push(FORMAT_VERSION);
+        for der in [&self.ark, &self.ask, &self.vek] {
+            out.extend_from_slice(&(der.len() as u32).to_be_bytes());
+            out.extend_from_slice(der);
+        }
+        out
+    }
+
+    /// Decodes the encoding produced by `to_bytes`. The certificates are not
+    /// parsed; use `to_chain` for that.
+    ///
+    /// # Errors
+    /// Returns an error if the magic or version is wrong, the buffer is
+    /// truncated, or bytes trail the VCEK.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let rest = bytes
+            .strip_prefix(MAGIC.as_slice())
+            .ok_or("Cached chain does not start with the expected magic")?;
+        let (&version, mut rest) = rest.split_first().ok_or("Cached chain is missing its version")?;
+        if version != FORMAT_VERSION {
+            return Err(format!("Unsupported cached chain format version {version}").into());
+        }
+        let mut next = || -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+            let (len, tail) = rest.split_first_chunk::<4>().ok_or("Cached chain is truncated")?;
+            let len = u32::from_be_bytes(*len) as usize;
+            let der = tail.get(..len).ok_or("Cached chain is truncated")?;
+            rest = &tail[len..];
+            Ok(der.to_vec())
+        };
+        let chain = SharedChain {
+            ark: next()?,
+            ask: next()?,
+            vek: next()?,
+        };
+        if !rest.is_empty() {
+            return Err("Cached chain has trailing bytes".into());
+        }
+        Ok(chain)
+    }
+}
+
+// Compile-time check that the wrapper, and the chain it stands in for, can
+// be shared across threads.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SharedChain>();
+    assert_send_sync::<Chain>();
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::generate_test_chain;
+    use std::sync::Arc;
+
+    #[test]
+    fn shares_chain_across_threads() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let shared = Arc::new(SharedChain::from_chain(&chain).unwrap());
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = Arc::clone(&shared);
+                std::thread::spawn(move || shared.to_chain().unwrap().vek.to_der().unwrap())
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), chain.vek.to_der().unwrap());
+        }
+    }
+
+    #[test]
+    fn round_trips_compact_encoding() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let shared = SharedChain::from_chain(&chain).unwrap();
+        let bytes = shared.to_bytes();
+        assert_eq!(&bytes[..4], b"SNPC");
+        assert_eq!(bytes[4], FORMAT_VERSION);
+        assert_eq!(bytes.len(), 5 + 12 + shared.ark.len() + shared.ask.len() + shared.vek.len());
+
+        let decoded = SharedChain::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, shared);
+        let reloaded = decoded.to_chain().unwrap();
+        assert_eq!(reloaded.vek.to_der().unwrap(), chain.vek.to_der().unwrap());
+    }
+
+    #[test]
+    fn rejects_truncated_or_padded_buffer() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let bytes = SharedChain::from_chain(&chain).unwrap().to_bytes();
+        for len in [4, 5, 8, bytes.len() - 1] {
+            assert!(SharedChain::from_bytes(&bytes[..len]).is_err(), "accepted {len} bytes");
+        }
+        let err = SharedChain::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(err.to_string(), "Cached chain is truncated");
+
+        let mut padded = bytes.clone();
+        padded.push(0);
+        let err = SharedChain::from_bytes(&padded).unwrap_err();
+        assert_eq!(err.to_string(), "Cached chain has trailing bytes");
+    }
+
+    #[test]
+    fn rejects_wrong_magic_or_version() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let bytes = SharedChain::from_chain(&chain).unwrap().to_bytes();
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[..4].copy_from_slice(b"SNPX");
+        let err = SharedChain::from_bytes(&bad_magic).unwrap_err();
+        assert_eq!(err.to_string(), "Cached chain does not start with the expected magic");
+
+        for version in [0, FORMAT_VERSION + 1] {
+            let mut bad_version = bytes.clone();
+            bad_version[4] = version;
+            let err = SharedChain::from_bytes(&bad_version).unwrap_err();
+            assert_eq!(err.to_string(), format!("Unsupported cached chain format version {version}"));
+        }
+    }
+}
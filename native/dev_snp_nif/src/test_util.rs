@@ -0,0 +1,263 @@
+//! Helpers for downstream tests that need a self-consistent chain and report
+//! without real AMD certificates. Keys are generated on every call and the
+//! resulting certificates must never be trusted outside of tests.
+
+use openssl::asn1::{Asn1Integer, Asn1Object, Asn1OctetString, Asn1Time};
+use openssl::bn::{BigNum, BigNumRef};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::ecdsa::EcdsaSig;
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::sha::sha384;
+use openssl::x509::extension::{BasicConstraints, KeyUsage};
+use openssl::x509::{X509Extension, X509Name, X509};
+use sev::certs::snp::{ca, Certificate, Chain};
+use sev::firmware::guest::AttestationReport;
+use sev::firmware::host::TcbVersion;
+#[cfg(test)]
+use crate::cert_table::{ARK_GUID, ASK_GUID, ENTRY_LEN};
+use crate::certs::{OID_BL_SPL, OID_SNP_SPL, OID_TEE_SPL, OID_UCODE_SPL};
+use crate::der::TAG_INTEGER;
+use crate::report::{parse_report_bytes, signed_bytes, MIN_REPORT_VERSION, REPORT_LEN, SIG_ALGO_ECDSA_P384_SHA384};
+
+/// Generates a fresh ECDSA P-384 key.
+pub(crate) fn generate_key() -> Result<PKey<Private>, Box<dyn std::error::Error>> {
+    let group = EcGroup::from_curve_name(Nid::SECP384R1)?;
+    Ok(PKey::from_ec_key(EcKey::generate(&group)?)?)
+}
+
+/// Builds a name with a single common name entry.
+fn name(cn: &str) -> Result<X509Name, Box<dyn std::error::Error>> {
+    let mut builder = X509Name::builder()?;
+    builder.append_entry_by_nid(Nid::COMMONNAME, cn)?;
+    Ok(builder.build())
+}
+
+/// Mints a certificate for `key` issued by `issuer_name`/`issuer_key`.
+pub(crate) fn mint(
+    subject: &str,
+    key: &PKey<Private>,
+    issuer: &str,
+    issuer_key: &PKey<Private>,
+    serial: u32,
+    is_ca: bool,
+) -> Result<Certificate, Box<dyn std::error::Error>> {
+    let extensions = if is_ca {
+        vec![
+            BasicConstraints::new().critical().ca().build()?,
+            KeyUsage::new().critical().key_cert_sign().crl_sign().build()?,
+        ]
+    } else {
+        vec![KeyUsage::new().critical().digital_signature().build()?]
+    };
+    mint_with(subject, key, issuer, issuer_key, serial, extensions)
+}
+
+/// Mints a certificate like `mint`, carrying exactly `extensions`.
+pub(crate) fn mint_with(
+    subject: &str,
+    key: &PKey<Private>,
+    issuer: &str,
+    issuer_key: &PKey<Private>,
+    serial: u32,
+    extensions: Vec<X509Extension>,
+) -> Result<Certificate, Box<dyn std::error::Error>> {
+    mint_valid_for(subject, key, issuer, issuer_key, serial, extensions, 365)
+}
+
+/// Mints a certificate like `mint_with`, valid from now for `days` days.
+pub(crate) fn mint_valid_for(
+    subject: &str,
+    key: &PKey<Private>,
+    issuer: &str,
+    issuer_key: &PKey<Private>,
+    serial: u32,
+    extensions: Vec<X509Extension>,
+    days: u32,
+) -> Result<Certificate, Box<dyn std::error::Error>> {
+    let serial = BigNum::from_u32(serial)?;
+    let serial = Asn1Integer::from_bn(&serial)?;
+    let (subject, issuer) = (name(subject)?, name(issuer)?);
+    let (not_before, not_after) = (Asn1Time::days_from_now(0)?, Asn1Time::days_from_now(days)?);
+
+    let mut builder = X509::builder()?;
+    builder.set_version(2)?;
+    builder.set_serial_number(&serial)?;
+    builder.set_subject_name(&subject)?;
+    builder.set_issuer_name(&issuer)?;
+    builder.set_pubkey(key)?;
+    builder.set_not_before(&not_before)?;
+    builder.set_not_after(&not_after)?;
+    for extension in extensions {
+        builder.append_extension(extension)?;
+    }
+    builder.sign(issuer_key, MessageDigest::sha384())?;
+    Ok(Certificate::from(builder.build()))
+}
+
+/// Builds a non-critical extension with the given dotted OID and
+/// DER-encoded value, for minting certificates that carry AMD extensions.
+pub(crate) fn custom_extension(oid: &str, value: &[u8]) -> Result<X509Extension, Box<dyn std::error::Error>> {
+    let oid = Asn1Object::from_str(oid)?;
+    let value = Asn1OctetString::new_from_bytes(value)?;
+    Ok(X509Extension::new_from_der(&oid, false, &value)?)
+}
+
+/// Builds the four SPL extensions AMD puts in a VCEK issued for `tcb`, each
+/// a DER INTEGER holding one component's SVN.
+pub(crate) fn spl_extensions(tcb: &TcbVersion) -> Result<Vec<X509Extension>, Box<dyn std::error::Error>> {
+    [
+        (OID_BL_SPL, tcb.bootloader),
+        (OID_TEE_SPL, tcb.tee),
+        (OID_SNP_SPL, tcb.snp),
+        (OID_UCODE_SPL, tcb.microcode),
+    ]
+    .into_iter()
+    .map(|(oid, svn)| {
+        // INTEGERs are signed, so SVNs with the top bit set need a leading zero.
+        let value = if svn >= 0x80 { vec![TAG_INTEGER, 2, 0, svn] } else { vec![TAG_INTEGER, 1, svn] };
+        custom_extension(oid, &value)
+    })
+    .collect()
+}
+
+/// Creates an empty directory under the system temp dir that no other test
+/// uses; the caller removes it when done.
+#[cfg(test)]
+pub(crate) fn temp_dir(label: &str) -> std::path::PathBuf {
+    let mut nonce = [0u8; 8];
+    openssl::rand::rand_bytes(&mut nonce).unwrap();
+    let dir = std::env::temp_dir().join(format!("dev_snp_nif-{label}-{}", hex::encode(nonce)));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Lays out a certificate table holding `certs`, followed by the
+/// terminating all-zero entry.
+#[cfg(test)]
+pub(crate) fn cert_table(certs: &[([u8; 16], Vec<u8>)]) -> Vec<u8> {
+    let mut offset = (certs.len() + 1) * ENTRY_LEN;
+    let mut table = Vec::new();
+    for (guid, der) in certs {
+        table.extend_from_slice(guid);
+        table.extend_from_slice(&(offset as u32).to_le_bytes());
+        table.extend_from_slice(&(der.len() as u32).to_le_bytes());
+        offset += der.len();
+    }
+    table.extend_from_slice(&[0; ENTRY_LEN]);
+    for (_, der) in certs {
+        table.extend_from_slice(der);
+    }
+    table
+}
+
+/// The table a guest would hand up for `chain`, with the endorsement key
+/// filed under `vek_guid`.
+#[cfg(test)]
+pub(crate) fn chain_table(chain: &Chain, vek_guid: [u8; 16]) -> Vec<u8> {
+    cert_table(&[
+        (ARK_GUID, chain.ca.ark.to_der().unwrap()),
+        (ASK_GUID, chain.ca.ask.to_der().unwrap()),
+        (vek_guid, chain.vek.to_der().unwrap()),
+    ])
+}
+
+/// Encodes a big-endian signature component in the report's 72-byte
+/// little-endian layout.
+fn le_component(component: &BigNumRef) -> Result<[u8; 72], Box<dyn std::error::Error>> {
+    let mut le: [u8; 72] = component
+        .to_vec_padded(72)?
+        .try_into()
+        .map_err(|_| "Signature component is longer than 72 bytes")?;
+    le.reverse();
+    Ok(le)
+}
+
+/// Mints an ARK and ASK from ephemeral keys, named the way AMD names the
+/// CAs of `product` (`ARK-<product>` and `SEV-<product>`).
+///
+/// # Errors
+/// Returns an error if key generation or certificate signing fails.
+pub fn generate_test_ca_chain(product: &str) -> Result<ca::Chain, Box<dyn std::error::Error>> {
+    let (ark_key, ask_key) = (generate_key()?, generate_key()?);
+    let (ark_name, ask_name) = (format!("ARK-{product}"), format!("SEV-{product}"));
+    Ok(ca::Chain {
+        ark: mint(&ark_name, &ark_key, &ark_name, &ark_key, 1, true)?,
+        ask: mint(&ask_name, &ask_key, &ark_name, &ark_key, 2, true)?,
+    })
+}
+
+/// Mints an ARK, ASK and VCEK from ephemeral keys, together with a version 2
+/// report signed by that VCEK.
+///
+/// The chain verifies and the report's signature verifies against it; every
+/// report field other than the version, signature algorithm and signature
+/// is zero. The VCEK carries SPL extensions for that all-zero reported TCB,
+/// as AMD issues VCEKs for a specific TCB.
+///
+/// # Errors
+/// Returns an error if key generation, certificate signing or report
+/// signing fails.
+pub fn generate_test_chain() -> Result<(Chain, AttestationReport), Box<dyn std::error::Error>> {
+    // Step 1: Mint the certificate hierarchy.
+    let (ark_key, ask_key, vek_key) = (generate_key()?, generate_key()?, generate_key()?);
+    let zero_tcb = TcbVersion {
+        bootloader: 0,
+        tee: 0,
+        snp: 0,
+        microcode: 0,
+        _reserved: [0; 4],
+    };
+    let mut vcek_extensions = vec![KeyUsage::new().critical().digital_signature().build()?];
+    vcek_extensions.extend(spl_extensions(&zero_tcb)?);
+    let chain = Chain {
+        ca: ca::Chain {
+            ark: mint("ARK-Test", &ark_key, "ARK-Test", &ark_key, 1, true)?,
+            ask: mint("SEV-Test", &ask_key, "ARK-Test", &ark_key, 2, true)?,
+        },
+        vek: mint_with("SEV-VCEK", &vek_key, "SEV-Test", &ask_key, 3, vcek_extensions)?,
+    };
+
+    // Step 2: Start from an all-zero report of the oldest supported layout,
+    // declaring the only signature algorithm SNP uses.
+    let mut raw = vec![0u8; REPORT_LEN];
+    raw[..4].copy_from_slice(&MIN_REPORT_VERSION.to_le_bytes());
+    raw[0x34..0x38].copy_from_slice(&SIG_ALGO_ECDSA_P384_SHA384.to_le_bytes());
+    let mut report = parse_report_bytes(&raw)?;
+
+    // Step 3: Sign the report with the VCEK key.
+    sign_report(&mut report, &vek_key)?;
+
+    Ok((chain, report))
+}
+
+/// Signs `report` in place with `key` over the region its version declares.
+pub(crate) fn sign_report(
+    report: &mut AttestationReport,
+    key: &PKey<Private>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let digest = sha384(&signed_bytes(report)?);
+    let signature = EcdsaSig::sign(&digest, &*key.ec_key()?)?;
+    report.signature.r = le_component(signature.r())?;
+    report.signature.s = le_component(signature.s())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certs::check_chain_roles;
+    use sev::certs::snp::Verifiable;
+
+    #[test]
+    fn generated_chain_verifies_and_signs_report() {
+        let (chain, report) = generate_test_chain().unwrap();
+        (&chain).verify().unwrap();
+        check_chain_roles(&chain).unwrap();
+        (&chain, &report).verify().unwrap();
+
+        let (other, _) = generate_test_chain().unwrap();
+        assert!((&other, &report).verify().is_err());
+    }
+}
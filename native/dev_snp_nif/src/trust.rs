@@ -0,0 +1,175 @@
+//! Sources of trusted AMD root certificates, decoupling where pinned roots
+//! are stored (in memory, bundled, or in an HSM) from chain verification.
+
+use sev::certs::snp::{Certificate, Chain, Verifiable};
+use crate::certs::eq_der;
+
+/// A store of pinned root certificates that chains are verified against.
+pub trait TrustStore {
+    /// Returns the trusted roots.
+    ///
+    /// # Errors
+    /// Returns an error if the roots cannot be loaded from the backing store.
+    fn roots(&self) -> Result<Vec<Certificate>, Box<dyn std::error::Error>>;
+}
+
+/// Roots already held in memory, e.g. loaded from configuration.
+impl TrustStore for Vec<Certificate> {
+    fn roots(&self) -> Result<Vec<Certificate>, Box<dyn std::error::Error>> {
+        Ok(self.clone())
+    }
+}
+
+/// Reports whether `ark` is byte-for-byte one of the trusted roots.
+///
+/// With the `bundled-roots` feature, a store holding no roots falls back to
+/// the AMD roots bundled with the crate.
+///
+/// # Errors
+/// Returns an error if the roots cannot be loaded or a certificate cannot
+/// be re-encoded.
+pub fn is_trusted_root(
+    ark: &Certificate,
+    trust_store: &dyn TrustStore,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let roots = trust_store.roots()?;
+    #[cfg(feature = "bundled-roots")]
+    let roots = if roots.is_empty() { crate::roots::bundled_roots()? } else { roots };
+    for root in &roots {
+        if eq_der(ark, root)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Verifies a chain whose ARK must be one of the trusted roots.
+///
+/// With the `bundled-roots` feature, a store holding no roots falls back to
+/// the AMD roots bundled with the crate.
+///
+/// # Arguments
+/// * `chain` - The chain to verify.
+/// * `trust_store` - Holds the roots the chain's ARK must match byte-for-byte.
+///
+/// # Errors
+/// Returns an error if the roots cannot be loaded, the ARK is not trusted,
+/// or the chain does not verify.
+pub fn verify_with_trusted_roots(
+    chain: &Chain,
+    trust_store: &dyn TrustStore,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !is_trusted_root(&chain.ca.ark, trust_store)? {
+        return Err("Chain ARK does not match any trusted root".into());
+    }
+    chain.verify()?;
+    Ok(())
+}
+
+/// Roots stored as certificate objects on a PKCS#11 token, such as an HSM.
+#[cfg(feature = "pkcs11")]
+pub struct Pkcs11TrustStore {
+    context: cryptoki::context::Pkcs11,
+    slot: cryptoki::slot::Slot,
+    label: Option<String>,
+}
+
+#[cfg(feature = "pkcs11")]
+impl Pkcs11TrustStore {
+    /// Loads and initializes the PKCS#11 module and selects a token.
+    ///
+    /// # Arguments
+    /// * `module` - Path to the vendor's PKCS#11 library.
+    /// * `slot_index` - Index of the token among the slots that hold one.
+    /// * `label` - Only load certificates with this label; `None` loads all.
+    ///
+    /// # Errors
+    /// Returns an error if the module cannot be loaded or initialized, or no
+    /// token is present at `slot_index`.
+    pub fn new<P: AsRef<std::path::Path>>(
+        module: P,
+        slot_index: usize,
+        label: Option<String>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let context = cryptoki::context::Pkcs11::new(module)?;
+        context.initialize(cryptoki::context::CInitializeArgs::OsThreads)?;
+        let slot = *context
+            .get_slots_with_token()?
+            .get(slot_index)
+            .ok_or_else(|| format!("No PKCS#11 token at slot index {slot_index}"))?;
+        Ok(Pkcs11TrustStore { context, slot, label })
+    }
+}
+
+#[cfg(feature = "pkcs11")]
+impl TrustStore for Pkcs11TrustStore {
+    fn roots(&self) -> Result<Vec<Certificate>, Box<dyn std::error::Error>> {
+        use cryptoki::object::{Attribute, AttributeType, ObjectClass};
+
+        let session = self.context.open_ro_session(self.slot)?;
+        let mut template = vec![Attribute::Class(ObjectClass::CERTIFICATE)];
+        if let Some(label) = &self.label {
+            template.push(Attribute::Label(label.as_bytes().to_vec()));
+        }
+        let mut roots = Vec::new();
+        for object in session.find_objects(&template)? {
+            for attribute in session.get_attributes(object, &[AttributeType::Value])? {
+                if let Attribute::Value(der) = attribute {
+                    roots.push(crate::certs::from_der(&der)?);
+                }
+            }
+        }
+        Ok(roots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::generate_test_chain;
+
+    /// A store that counts how often its roots are loaded.
+    struct InMemoryStore {
+        roots: Vec<Certificate>,
+        loads: std::cell::Cell<usize>,
+    }
+
+    impl TrustStore for InMemoryStore {
+        fn roots(&self) -> Result<Vec<Certificate>, Box<dyn std::error::Error>> {
+            self.loads.set(self.loads.get() + 1);
+            Ok(self.roots.clone())
+        }
+    }
+
+    /// A store whose backing storage is unavailable.
+    struct UnavailableStore;
+
+    impl TrustStore for UnavailableStore {
+        fn roots(&self) -> Result<Vec<Certificate>, Box<dyn std::error::Error>> {
+            Err("token not present".into())
+        }
+    }
+
+    #[test]
+    fn verifies_chain_against_in_memory_store() {
+        let (chain, _) = generate_test_chain().unwrap();
+        let (other, _) = generate_test_chain().unwrap();
+
+        let store = InMemoryStore {
+            roots: vec![other.ca.ark.clone(), chain.ca.ark.clone()],
+            loads: Default::default(),
+        };
+        verify_with_trusted_roots(&chain, &store).unwrap();
+        assert_eq!(store.loads.get(), 1);
+
+        let store = InMemoryStore {
+            roots: vec![other.ca.ark.clone()],
+            loads: Default::default(),
+        };
+        let err = verify_with_trusted_roots(&chain, &store).unwrap_err();
+        assert_eq!(err.to_string(), "Chain ARK does not match any trusted root");
+
+        let err = verify_with_trusted_roots(&chain, &UnavailableStore).unwrap_err();
+        assert_eq!(err.to_string(), "token not present");
+    }
+}
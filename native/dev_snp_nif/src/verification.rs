@@ -1,11 +1,21 @@
 use rustler::{Binary, Encoder, Env, NifResult, Term};
-use rustler::types::atom::{self, ok};
-use serde_json::Value;
+use rustler::types::atom;
+#[cfg(all(feature = "kds-fetch", feature = "bundled-roots"))]
+use rustler::types::atom::ok;
 use serde::Deserialize;
-use sev::certs::snp::{ecdsa::Signature, Chain, Verifiable};
-use sev::firmware::host::TcbVersion;
-use sev::firmware::guest::{AttestationReport, GuestPolicy, PlatformInfo};
-use crate::helpers::{request_cert_chain, request_vcek};
+#[cfg(all(feature = "kds-fetch", feature = "bundled-roots"))]
+use sev::certs::snp::Verifiable;
+use crate::ct::ct_eq;
+#[cfg(all(feature = "kds-fetch", feature = "bundled-roots"))]
+use crate::helpers::fetch_and_verify_chain;
+#[cfg(all(feature = "kds-fetch", feature = "bundled-roots"))]
+use crate::kds::HttpKdsStore;
+#[cfg(all(feature = "kds-fetch", feature = "bundled-roots"))]
+use crate::product::ProductLine;
+#[cfg(all(feature = "kds-fetch", feature = "bundled-roots"))]
+use crate::report::{parse_report, report_product_line};
+#[cfg(all(feature = "kds-fetch", feature = "bundled-roots"))]
+use crate::roots::bundled_root;
 use crate::logging::log_message;
 
 /// Verifies whether the measurement in the attestation report matches the expected measurement.
@@ -75,7 +85,7 @@ fn verify_measurement<'a>(
     // );
 
     // Step 4: Compare the actual and expected measurements.
-    if actual_measurement == &expected_measurement {
+    if ct_eq(actual_measurement, &expected_measurement) {
         //log_message("INFO", file!(), line!(), "Measurements match.");
         Ok((atom::ok(), true).encode(env))
     } else {
@@ -90,211 +100,85 @@ fn verify_measurement<'a>(
 /// # Arguments
 /// * `env` - The Rustler environment, used to encode the return value.
 /// * `report` - A binary containing the serialized attestation report.
+/// * `product` - The KDS product name (e.g., "Milan") of the CPU that produced
+///   the report. Only used for version 2 reports, which do not identify the
+///   CPU; may be empty for version 3 reports.
 ///
 /// # Returns
 /// A tuple with:
 /// - `ok` atom and a success message if the signature is valid.
 /// - `error` atom and an error message if the signature verification fails.
+#[cfg(all(feature = "kds-fetch", feature = "bundled-roots"))]
 #[rustler::nif]
 fn verify_signature<'a>(
     env: Env<'a>,
     report: Binary<'a>,
+    product: String,
 ) ->  NifResult<Term<'a>>  {
     // log_message("INFO", file!(), line!(), "Verifying signature...");
 
-    // Step 1: Parse the report JSON into a serde Value object.
-    let json_data = match serde_json::from_slice::<Value>(report.as_slice()) {
-        Ok(data) => data,
+    // Step 1: Parse the report JSON into an AttestationReport, rejecting
+    // unsupported structure versions.
+    let attestation_report = match parse_report(report.as_slice()) {
+        Ok(parsed_report) => parsed_report,
         Err(err) => {
-            return Ok((
-                rustler::types::atom::error(),
-                format!("Failed to parse JSON: {}", err),
-            )
-                .encode(env));
+            let msg = err.to_string();
+            log_message("ERROR", file!(), line!(), &msg);
+            return Ok((atom::error(), msg).encode(env));
         }
     };
 
-    // Step 2: Map JSON fields to the AttestationReport struct.
-    // Each field is individually parsed to ensure type safety.
-    let attestation_report = AttestationReport {
-        version: json_data["version"].as_u64().unwrap_or(0) as u32,
-        guest_svn: json_data["guest_svn"].as_u64().unwrap_or(0) as u32,
-        policy: GuestPolicy(json_data["policy"].as_u64().unwrap_or(0)),
-        family_id: json_data["family_id"]
-            .as_array()
-            .unwrap_or(&vec![])
-            .iter()
-            .map(|v| v.as_u64().unwrap_or(0) as u8)
-            .collect::<Vec<u8>>()
-            .try_into()
-            .unwrap_or([0; 16]),
-        image_id: json_data["image_id"]
-            .as_array()
-            .unwrap_or(&vec![])
-            .iter()
-            .map(|v| v.as_u64().unwrap_or(0) as u8)
-            .collect::<Vec<u8>>()
-            .try_into()
-            .unwrap_or([0; 16]),
-        vmpl: json_data["vmpl"].as_u64().unwrap_or(0) as u32,
-        sig_algo: json_data["sig_algo"].as_u64().unwrap_or(0) as u32,
-        current_tcb: TcbVersion {
-            bootloader: json_data["current_tcb"]["bootloader"].as_u64().unwrap_or(0) as u8,
-            tee: json_data["current_tcb"]["tee"].as_u64().unwrap_or(0) as u8,
-            snp: json_data["current_tcb"]["snp"].as_u64().unwrap_or(0) as u8,
-            microcode: json_data["current_tcb"]["microcode"].as_u64().unwrap_or(0) as u8,
-            _reserved: [0; 4],
-        },
-        plat_info: PlatformInfo(json_data["plat_info"].as_u64().unwrap_or(0)),
-        _author_key_en: json_data["_author_key_en"].as_u64().unwrap_or(0) as u32,
-        _reserved_0: json_data["_reserved_0"].as_u64().unwrap_or(0) as u32,
-        report_data: json_data["report_data"]
-            .as_array()
-            .unwrap_or(&vec![])
-            .iter()
-            .map(|v| v.as_u64().unwrap_or(0) as u8)
-            .collect::<Vec<u8>>()
-            .try_into()
-            .unwrap_or([0; 64]),
-        measurement: json_data["measurement"]
-            .as_array()
-            .unwrap_or(&vec![])
-            .iter()
-            .map(|v| v.as_u64().unwrap_or(0) as u8)
-            .collect::<Vec<u8>>()
-            .try_into()
-            .unwrap_or([0; 48]),
-        host_data: json_data["host_data"]
-            .as_array()
-            .unwrap_or(&vec![])
-            .iter()
-            .map(|v| v.as_u64().unwrap_or(0) as u8)
-            .collect::<Vec<u8>>()
-            .try_into()
-            .unwrap_or([0; 32]),
-        id_key_digest: json_data["id_key_digest"]
-            .as_array()
-            .unwrap_or(&vec![])
-            .iter()
-            .map(|v| v.as_u64().unwrap_or(0) as u8)
-            .collect::<Vec<u8>>()
-            .try_into()
-            .unwrap_or([0; 48]),
-        author_key_digest: json_data["author_key_digest"]
-            .as_array()
-            .unwrap_or(&vec![])
-            .iter()
-            .map(|v| v.as_u64().unwrap_or(0) as u8)
-            .collect::<Vec<u8>>()
-            .try_into()
-            .unwrap_or([0; 48]),
-        report_id: json_data["report_id"]
-            .as_array()
-            .unwrap_or(&vec![])
-            .iter()
-            .map(|v| v.as_u64().unwrap_or(0) as u8)
-            .collect::<Vec<u8>>()
-            .try_into()
-            .unwrap_or([0; 32]),
-        report_id_ma: json_data["report_id_ma"]
-            .as_array()
-            .unwrap_or(&vec![])
-            .iter()
-            .map(|v| v.as_u64().unwrap_or(0) as u8)
-            .collect::<Vec<u8>>()
-            .try_into()
-            .unwrap_or([0; 32]),
-        reported_tcb: TcbVersion {
-            bootloader: json_data["reported_tcb"]["bootloader"]
-                .as_u64()
-                .unwrap_or(0) as u8,
-            tee: json_data["reported_tcb"]["tee"].as_u64().unwrap_or(0) as u8,
-            snp: json_data["reported_tcb"]["snp"].as_u64().unwrap_or(0) as u8,
-            microcode: json_data["reported_tcb"]["microcode"].as_u64().unwrap_or(0) as u8,
-            _reserved: [0; 4],
-        },
-        _reserved_1: [0; 24],
-        chip_id: json_data["chip_id"]
-            .as_array()
-            .unwrap_or(&vec![])
-            .iter()
-            .map(|v| v.as_u64().unwrap_or(0) as u8)
-            .collect::<Vec<u8>>()
-            .try_into()
-            .unwrap_or([0; 64]),
-        committed_tcb: TcbVersion {
-            bootloader: json_data["committed_tcb"]["bootloader"]
-                .as_u64()
-                .unwrap_or(0) as u8,
-            tee: json_data["committed_tcb"]["tee"].as_u64().unwrap_or(0) as u8,
-            snp: json_data["committed_tcb"]["snp"].as_u64().unwrap_or(0) as u8,
-            microcode: json_data["committed_tcb"]["microcode"]
-                .as_u64()
-                .unwrap_or(0) as u8,
-            _reserved: [0; 4],
-        },
-        current_build: json_data["current_build"].as_u64().unwrap_or(0) as u8,
-        current_minor: json_data["current_minor"].as_u64().unwrap_or(0) as u8,
-        current_major: json_data["current_major"].as_u64().unwrap_or(0) as u8,
-        _reserved_2: json_data["_reserved_2"].as_u64().unwrap_or(0) as u8,
-        committed_build: json_data["committed_build"].as_u64().unwrap_or(0) as u8,
-        committed_minor: json_data["committed_minor"].as_u64().unwrap_or(0) as u8,
-        committed_major: json_data["committed_major"].as_u64().unwrap_or(0) as u8,
-        _reserved_3: json_data["_reserved_3"].as_u64().unwrap_or(0) as u8,
-        launch_tcb: TcbVersion {
-            bootloader: json_data["launch_tcb"]["bootloader"].as_u64().unwrap_or(0) as u8,
-            tee: json_data["launch_tcb"]["tee"].as_u64().unwrap_or(0) as u8,
-            snp: json_data["launch_tcb"]["snp"].as_u64().unwrap_or(0) as u8,
-            microcode: json_data["launch_tcb"]["microcode"].as_u64().unwrap_or(0) as u8,
-            _reserved: [0; 4],
-        },
-        _reserved_4: [0; 168],
-        signature: Signature {
-            r: json_data["signature"]["r"]
-                .as_array()
-                .unwrap_or(&vec![])
-                .iter()
-                .map(|v| v.as_u64().unwrap_or(0) as u8)
-                .collect::<Vec<u8>>()
-                .try_into()
-                .unwrap_or([0; 72]),
-            s: json_data["signature"]["s"]
-                .as_array()
-                .unwrap_or(&vec![])
-                .iter()
-                .map(|v| v.as_u64().unwrap_or(0) as u8)
-                .collect::<Vec<u8>>()
-                .try_into()
-                .unwrap_or([0; 72]),
-            _reserved: [0; 368],
+    // Step 2: Extract the chip ID and the reported TCB, which is the TCB
+    // the VCEK is certified for.
+    let chip_id_array: [u8; 64] = attestation_report.chip_id;
+    let tcb_version = attestation_report.reported_tcb;
+
+    // Step 3: Determine the product line, from the report's CPUID fields when
+    // it carries them and from the caller otherwise.
+    let fallback = match product.as_str() {
+        "" => None,
+        name => match ProductLine::from_name(name) {
+            Some(product) => Some(product),
+            None => {
+                let msg = format!("Unknown product line: {name}");
+                log_message("ERROR", file!(), line!(), &msg);
+                return Ok((atom::error(), msg).encode(env));
+            }
         },
     };
+    let product = match report_product_line(&attestation_report, fallback) {
+        Ok(product) => product,
+        Err(msg) => {
+            log_message("ERROR", file!(), line!(), &msg);
+            return Ok((atom::error(), msg).encode(env));
+        }
+    };
 
-    // Step 3: Extract the chip ID and TCB version.
-    let chip_id_array: [u8; 64] = attestation_report
-        .chip_id
-        .try_into()
-        .expect("chip_id must be 64 bytes");
-    let tcb_version = attestation_report.current_tcb;
-
-    // Step 4: Request the certificate chain and VCEK.
-    let ca = request_cert_chain("Milan").unwrap();
-    let vcek = request_vcek(chip_id_array, tcb_version).unwrap();
-
-    // Step 5: Verify the certificate chain.
-    if let Err(e) = ca.verify() {
-        log_message(
-            "ERROR",
-            file!(),
-            line!(),
-            &format!("CA chain verification failed: {:?}", e),
-        );
-        return Ok((atom::error(), format!("CA verification failed: {:?}", e)).encode(env));
-    }
-    //log_message("INFO", file!(), line!(), "CA chain verification successful.");
+    // Step 4: Fetch the certificate chain for this platform and verify it
+    // against the bundled AMD root.
+    let cert_chain = match bundled_root(product).and_then(|root| {
+        fetch_and_verify_chain(
+            &HttpKdsStore::default(),
+            &vec![root],
+            product.kds_segment(),
+            chip_id_array,
+            tcb_version,
+        )
+    }) {
+        Ok(chain) => chain,
+        Err(e) => {
+            log_message(
+                "ERROR",
+                file!(),
+                line!(),
+                &format!("Certificate chain verification failed: {:?}", e),
+            );
+            return Ok((atom::error(), format!("Chain verification failed: {:?}", e)).encode(env));
+        }
+    };
+    //log_message("INFO", file!(), line!(), "Certificate chain verification successful.");
 
-    // Step 6: Verify the attestation report.
-    let cert_chain = Chain { ca, vek: vcek };
+    // Step 5: Verify the attestation report.
     if let Err(e) = (&cert_chain, &attestation_report).verify() {
         log_message(
             "ERROR",
@@ -308,3 +192,19 @@ fn verify_signature<'a>(
     //log_message("INFO", file!(), line!(), "Signature verification successful.");
     Ok((ok(), true).encode(env))
 }
+
+/// Stands in for `verify_signature` when the crate is built without the
+/// `kds-fetch` and `bundled-roots` features, so the NIF the Erlang module
+/// exports is always loaded.
+///
+/// # Returns
+/// `{error, <<"feature disabled">>}`.
+#[cfg(not(all(feature = "kds-fetch", feature = "bundled-roots")))]
+#[rustler::nif]
+fn verify_signature<'a>(
+    env: Env<'a>,
+    _report: Binary<'a>,
+    _product: String,
+) -> NifResult<Term<'a>> {
+    Ok((atom::error(), "feature disabled").encode(env))
+}
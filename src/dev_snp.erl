@@ -141,7 +141,10 @@ verify(M1, M2, NodeOpts) ->
     {ok, MeasurementIsValid} = dev_snp_nif:verify_measurement(ReportJSON, list_to_binary(Expected)),
     ?event({measurement_is_valid, MeasurementIsValid}),
     % Step 6: Check the report's integrity.
-    {ok, ReportIsValid} = dev_snp_nif:verify_signature(ReportJSON),
+    % Version 2 reports do not identify the CPU, so fall back to the product
+    % line the node is configured with.
+    Product = hb_opts:get(snp_product_line, <<"Milan">>, NodeOpts),
+    {ok, ReportIsValid} = dev_snp_nif:verify_signature(ReportJSON, Product),
 	?event({report_is_valid, ReportIsValid}),
     Valid =
         lists:all(
@@ -1,6 +1,6 @@
 -module(dev_snp_nif).
 -export([generate_attestation_report/2, compute_launch_digest/1, check_snp_support/0]).
--export([verify_measurement/2, verify_signature/1]).
+-export([verify_measurement/2, verify_signature/2]).
 -include("include/cargo.hrl").
 -include("include/hb.hrl").
 -include_lib("eunit/include/eunit.hrl").
@@ -20,7 +20,7 @@ compute_launch_digest(_Args) ->
 verify_measurement(_Report, _Expected) ->
 	?NOT_LOADED.
 
-verify_signature(_Report) ->
+verify_signature(_Report, _Product) ->
 	?NOT_LOADED.
 
 init() ->
@@ -78,8 +78,29 @@ verify_measurement_test() ->
 	Result = dev_snp_nif:verify_measurement(MockReport, ExpectedMeasurement),
 	?assertMatch({ok, true}, Result).
 
+verify_measurement_mismatch_test() ->
+	%% A measurement of the right length that differs only in its last byte
+    {ok, MockReport} = file:read_file("test/snp-measurement.json"),
+	WrongMeasurement = <<94,87,4,197,20,11,255,129,179,197,146,104,8,212,152,248,110,11,60,246,82,254,24,55,201,47,157,229,163,82,108,66,191,138,241,229,40,144,133,170,116,109,17,62,20,241,144,120>>,
+	Result = dev_snp_nif:verify_measurement(MockReport, WrongMeasurement),
+	?assertMatch({error, false}, Result).
+
 verify_signature_test() ->
 	%% Define a mock report (JSON string) as binary
     {ok, MockAttestation} = file:read_file("test/snp-attestation.json"),
-	Result = dev_snp_nif:verify_signature(MockAttestation),
+	Result = dev_snp_nif:verify_signature(MockAttestation, <<"Milan">>),
 	?assertMatch({ok, true}, Result).
+
+verify_signature_unsupported_version_test() ->
+	%% Bump the report version past the newest layout the NIF understands
+    {ok, MockAttestation} = file:read_file("test/snp-attestation.json"),
+	FutureReport =
+		binary:replace(MockAttestation, <<"\"version\":2">>, <<"\"version\":9">>),
+	Result = dev_snp_nif:verify_signature(FutureReport, <<"Milan">>),
+	?assertMatch({error, _}, Result).
+
+verify_signature_requires_product_for_v2_test() ->
+	%% Version 2 reports do not identify the CPU, so the product must be given
+    {ok, MockAttestation} = file:read_file("test/snp-attestation.json"),
+	Result = dev_snp_nif:verify_signature(MockAttestation, <<>>),
+	?assertMatch({error, _}, Result).